@@ -0,0 +1,424 @@
+use crate::calc::common::{Float, Integer, Rational};
+use crate::calc::format::{exponent, Format, Notation};
+use bigdecimal::{BigDecimal, Context as DecimalContext, RoundingMode};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+
+// extra digits carried by every value so rounding errors stay below the
+// displayed precision
+const GUARD_DIGITS: u64 = 10;
+const MAX_ITERATIONS: usize = 100_000;
+// results are kept well inside the 64-bit scale of the decimal type, so
+// products of them cannot overflow it either
+const MAX_EXPONENT: Float = 1e15;
+// reducing an angle takes pi to as many digits as the angle has
+const MAX_ANGLE_DIGITS: i64 = 1000;
+
+#[derive(Clone, PartialEq)]
+pub struct BigFloat {
+    value: BigDecimal,
+    prec: u64,
+}
+
+impl BigFloat {
+    pub fn new(value: BigDecimal, prec: u64) -> BigFloat {
+        BigFloat { value: value.with_prec(prec + GUARD_DIGITS), prec }
+    }
+
+    pub fn from_float(f: Float, prec: u64) -> Option<BigFloat> {
+        if !f.is_finite() {
+            return Option::None;
+        }
+        // shortest round-trip representation, so a literal like 0.1 stays exact
+        match BigDecimal::from_str(&f.to_string()) {
+            Result::Ok(v) => Option::Some(BigFloat::new(v, prec)),
+            Result::Err(_) => Option::None,
+        }
+    }
+
+    pub fn from_integer(i: &Integer, prec: u64) -> BigFloat {
+        BigFloat::new(BigDecimal::from(i.clone()), prec)
+    }
+
+    pub fn from_ratio(r: &Rational, prec: u64) -> BigFloat {
+        let numer = BigDecimal::from(r.numer().clone());
+        let denom = BigDecimal::from(r.denom().clone());
+        BigFloat::new(div(&numer, &denom, prec + GUARD_DIGITS), prec)
+    }
+
+    pub fn pi(prec: u64) -> BigFloat {
+        BigFloat::new(pi(prec + GUARD_DIGITS), prec)
+    }
+
+    pub fn e(prec: u64) -> BigFloat {
+        BigFloat::new(exp(&BigDecimal::one(), prec + GUARD_DIGITS), prec)
+    }
+
+    pub fn phi(prec: u64) -> BigFloat {
+        let w = prec + GUARD_DIGITS;
+        let root5 = BigDecimal::from(5).sqrt_with_context(&context(w)).unwrap_or_default();
+        BigFloat::new((root5 + BigDecimal::one()).half(), prec)
+    }
+
+    pub fn prec(&self) -> u64 {
+        self.prec
+    }
+
+    pub fn to_float(&self) -> Float {
+        self.value.to_f64().unwrap_or(Float::NAN)
+    }
+
+    pub fn trunc(&self) -> Integer {
+        self.round_with(RoundingMode::Down)
+    }
+
+    pub fn floor(&self) -> Integer {
+        self.round_with(RoundingMode::Floor)
+    }
+
+    pub fn ceil(&self) -> Integer {
+        self.round_with(RoundingMode::Ceiling)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.value.is_integer()
+    }
+
+    pub fn neg(&self) -> BigFloat {
+        BigFloat::new(-&self.value, self.prec)
+    }
+
+    pub fn abs(&self) -> BigFloat {
+        BigFloat::new(self.value.abs(), self.prec)
+    }
+
+    pub fn add(&self, rhs: &BigFloat) -> BigFloat {
+        BigFloat::new(&self.value + &rhs.value, self.prec.max(rhs.prec))
+    }
+
+    pub fn sub(&self, rhs: &BigFloat) -> BigFloat {
+        BigFloat::new(&self.value - &rhs.value, self.prec.max(rhs.prec))
+    }
+
+    pub fn mul(&self, rhs: &BigFloat) -> BigFloat {
+        BigFloat::new(&self.value * &rhs.value, self.prec.max(rhs.prec))
+    }
+
+    pub fn div(&self, rhs: &BigFloat) -> BigFloat {
+        let prec = self.prec.max(rhs.prec);
+        BigFloat::new(div(&self.value, &rhs.value, prec + GUARD_DIGITS), prec)
+    }
+
+    pub fn rem(&self, rhs: &BigFloat) -> BigFloat {
+        BigFloat::new(&self.value % &rhs.value, self.prec.max(rhs.prec))
+    }
+
+    pub fn powi(&self, n: i64) -> BigFloat {
+        let w = self.prec + GUARD_DIGITS;
+        let power = self.value.powi_with_context(n.abs(), &context(w));
+        if n < 0 {
+            return BigFloat::new(div(&BigDecimal::one(), &power, w), self.prec);
+        }
+        BigFloat::new(power, self.prec)
+    }
+
+    // whether self ** rhs has a decimal exponent beyond what is kept
+    pub fn pow_overflows(&self, rhs: &BigFloat) -> bool {
+        !self.is_zero() && self.log10().abs().log10() + rhs.log10() > MAX_EXPONENT.log10()
+    }
+
+    pub fn powf(&self, rhs: &BigFloat) -> Option<BigFloat> {
+        let prec = self.prec.max(rhs.prec);
+        let w = prec + GUARD_DIGITS;
+        let ln = ln(&self.value, w)?;
+        Option::Some(BigFloat::new(exp(&(ln * &rhs.value), w), prec))
+    }
+
+    pub fn sqrt(&self) -> Option<BigFloat> {
+        let w = self.prec + GUARD_DIGITS;
+        let root = self.value.sqrt_with_context(&context(w))?;
+        Option::Some(BigFloat::new(root, self.prec))
+    }
+
+    pub fn ln(&self) -> Option<BigFloat> {
+        let value = ln(&self.value, self.prec + GUARD_DIGITS)?;
+        Option::Some(BigFloat::new(value, self.prec))
+    }
+
+    pub fn exp(&self) -> Option<BigFloat> {
+        if self.log10() > (MAX_EXPONENT * std::f64::consts::LN_10).log10() {
+            return Option::None;
+        }
+        Option::Some(BigFloat::new(exp(&self.value, self.prec + GUARD_DIGITS), self.prec))
+    }
+
+    pub fn sin(&self) -> Option<BigFloat> {
+        self.reducible()?;
+        Option::Some(BigFloat::new(sin(&self.value, self.prec + GUARD_DIGITS), self.prec))
+    }
+
+    pub fn cos(&self) -> Option<BigFloat> {
+        self.reducible()?;
+        Option::Some(BigFloat::new(cos(&self.value, self.prec + GUARD_DIGITS), self.prec))
+    }
+
+    pub fn tan(&self) -> Option<BigFloat> {
+        self.reducible()?;
+        let w = self.prec + GUARD_DIGITS;
+        let tan = div(&sin(&self.value, w), &cos(&self.value, w), w);
+        Option::Some(BigFloat::new(tan, self.prec))
+    }
+
+    fn reducible(&self) -> Option<()> {
+        (self.value.order_of_magnitude() <= MAX_ANGLE_DIGITS).then_some(())
+    }
+
+    // keeps the leading digits so values beyond the float range still have a log
+    fn log10(&self) -> Float {
+        let (digits, scale) = self.value.as_bigint_and_exponent();
+        let shift = digits.bits().saturating_sub(64);
+        let mantissa = (digits.abs() >> shift).to_f64().unwrap_or(Float::NAN);
+        (mantissa.log2() + shift as Float) / std::f64::consts::LOG2_10 - scale as Float
+    }
+
+    pub fn cmp(&self, rhs: &BigFloat) -> Ordering {
+        self.value.cmp(&rhs.value)
+    }
+
+    fn round_with(&self, mode: RoundingMode) -> Integer {
+        self.value.with_scale_round(0, mode).into_bigint_and_scale().0
+    }
+}
+
+fn context(prec: u64) -> DecimalContext {
+    DecimalContext::default()
+        .with_prec(prec.max(1))
+        .unwrap_or_default()
+}
+
+fn epsilon(prec: u64) -> BigDecimal {
+    BigDecimal::new(Integer::one(), prec as i64)
+}
+
+fn div(lhs: &BigDecimal, rhs: &BigDecimal, prec: u64) -> BigDecimal {
+    let ctx = context(prec);
+    lhs.mul_with_context(&rhs.inverse_with_context(&ctx), &ctx)
+}
+
+fn div_int(lhs: &BigDecimal, rhs: u64, prec: u64) -> BigDecimal {
+    // long division by a machine integer, much cheaper than a full inverse
+    let (digits, scale) = lhs.as_bigint_and_exponent();
+    let shift = prec as i64 + 20 - lhs.digits() as i64;
+    let value = if shift > 0 {
+        digits * num_traits::pow(Integer::from(10), shift as usize) / rhs
+    } else {
+        digits / rhs
+    };
+    BigDecimal::new(value, scale + shift.max(0)).with_prec(prec)
+}
+
+fn pi(prec: u64) -> BigDecimal {
+    // Machin's formula: pi = 16 atan(1/5) - 4 atan(1/239)
+    let w = prec + GUARD_DIGITS;
+    let value = atan_inv(5, w) * BigDecimal::from(16) - atan_inv(239, w) * BigDecimal::from(4);
+    value.with_prec(prec)
+}
+
+fn atan_inv(n: u64, prec: u64) -> BigDecimal {
+    let eps = epsilon(prec);
+    let mut power = div_int(&BigDecimal::one(), n, prec);
+    let mut sum = power.clone();
+
+    for k in 1..MAX_ITERATIONS {
+        power = div_int(&power, n * n, prec);
+        let term = div_int(&power, 2 * k as u64 + 1, prec);
+        if term < eps {
+            break;
+        }
+        if k % 2 == 1 {
+            sum -= term;
+        } else {
+            sum += term;
+        }
+    }
+    sum
+}
+
+fn exp(x: &BigDecimal, prec: u64) -> BigDecimal {
+    // halve the argument until the series converges quickly, then square back
+    let magnitude = x.abs().to_f64().unwrap_or(Float::MAX);
+    let halvings = if magnitude > 0.5 { (magnitude / 0.5).log2().ceil() as u64 } else { 0 };
+    let w = prec + halvings / 3 + 2;
+
+    let mut r = x.clone();
+    for _ in 0..halvings {
+        r = r.half();
+    }
+
+    let eps = epsilon(w);
+    let mut sum = BigDecimal::one();
+    let mut term = BigDecimal::one();
+    for n in 1..MAX_ITERATIONS {
+        term = div_int(&(term * &r), n as u64, w);
+        if term.abs() < eps {
+            break;
+        }
+        sum += &term;
+    }
+
+    for _ in 0..halvings {
+        sum = sum.square().with_prec(w);
+    }
+    sum.with_prec(prec)
+}
+
+fn ln(x: &BigDecimal, prec: u64) -> Option<BigDecimal> {
+    if !x.is_positive() {
+        return Option::None;
+    }
+
+    // x = m * 10^k with 1 <= m < 10, so ln(x) = ln(m) + k ln(10)
+    let k = x.order_of_magnitude();
+    let (digits, scale) = x.as_bigint_and_exponent();
+    let m = BigDecimal::new(digits, scale + k);
+
+    let mut value = ln_newton(&m, prec + GUARD_DIGITS);
+    if k != 0 {
+        value += ln_newton(&BigDecimal::from(10), prec + GUARD_DIGITS) * BigDecimal::from(k);
+    }
+    Option::Some(value.with_prec(prec))
+}
+
+fn ln_newton(x: &BigDecimal, prec: u64) -> BigDecimal {
+    // Halley iteration on exp(y) = x, seeded from the f64 logarithm; each
+    // step roughly triples the number of correct digits
+    let eps = epsilon(prec);
+    let seed = x.to_f64().unwrap_or(1.0).ln();
+    let mut y = BigDecimal::from_str(&seed.to_string()).unwrap_or_default();
+    let mut correct = 5;
+
+    while correct < prec {
+        let ey = exp(&y, prec);
+        let delta = div(&((x - &ey) * BigDecimal::from(2)), &(x + &ey), prec);
+        y = (y + &delta).with_prec(prec);
+        if delta.abs() < eps {
+            break;
+        }
+        correct *= 3;
+    }
+    y
+}
+
+fn reduce_angle(x: &BigDecimal, prec: u64) -> BigDecimal {
+    let w = prec + x.order_of_magnitude().max(0) as u64;
+    let two_pi = pi(w).double();
+    let turns = div(x, &two_pi, w).with_scale_round(0, RoundingMode::HalfEven);
+    (x - turns * two_pi).with_prec(prec)
+}
+
+fn sin(x: &BigDecimal, prec: u64) -> BigDecimal {
+    let r = reduce_angle(x, prec);
+    let r2 = r.square().with_prec(prec);
+    let eps = epsilon(prec);
+
+    let mut term = r.clone();
+    let mut sum = r;
+    for n in 1..MAX_ITERATIONS {
+        term = -div_int(&(term * &r2), (2 * n * (2 * n + 1)) as u64, prec);
+        if term.abs() < eps {
+            break;
+        }
+        sum += &term;
+    }
+    sum
+}
+
+fn cos(x: &BigDecimal, prec: u64) -> BigDecimal {
+    let r = reduce_angle(x, prec);
+    let r2 = r.square().with_prec(prec);
+    let eps = epsilon(prec);
+
+    let mut term = BigDecimal::one();
+    let mut sum = BigDecimal::one();
+    for n in 1..MAX_ITERATIONS {
+        term = -div_int(&(term * &r2), ((2 * n - 1) * (2 * n)) as u64, prec);
+        if term.abs() < eps {
+            break;
+        }
+        sum += &term;
+    }
+    sum
+}
+
+// plain decimals unless that would take zeros past the precision or
+// ahead of the first digit, as f64's Debug does
+impl fmt::Display for BigFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prec = NonZeroU64::new(self.prec).unwrap_or(NonZeroU64::MIN);
+        let value = self.value.with_precision_round(prec, RoundingMode::HalfEven);
+        let exp = exponent(&value);
+        let notation = if exp < -4 || exp >= self.prec as i64 {
+            Notation::Scientific
+        } else {
+            Notation::Plain
+        };
+        let format = Format { notation, ..Format::default() };
+        write!(f, "{}", format.number(&value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants_to_fifty_digits() {
+        assert_eq!(
+            BigFloat::pi(50).to_string(),
+            "3.1415926535897932384626433832795028841971693993751"
+        );
+        assert_eq!(
+            BigFloat::phi(50).to_string(),
+            "1.6180339887498948482045868343656381177203091798058"
+        );
+    }
+
+    #[test]
+    fn transcendental_functions() {
+        let one = BigFloat::from_integer(&Integer::one(), 40);
+        assert_eq!(
+            one.sin().unwrap().to_string(),
+            "0.8414709848078965066525023216302989996226"
+        );
+        let ten = BigFloat::from_integer(&Integer::from(10), 40);
+        assert_eq!(
+            ten.ln().unwrap().to_string(),
+            "2.302585092994045684017991454684364207601"
+        );
+    }
+
+    #[test]
+    fn display_rounds_off_guard_digits() {
+        let half = BigFloat::from_float(0.5, 60).unwrap();
+        let two = BigFloat::from_float(2.0, 60).unwrap();
+        assert_eq!(half.ln().unwrap().div(&two.ln().unwrap()).to_string(), "-1");
+        let one = BigFloat::from_integer(&Integer::one(), 20);
+        let big = BigFloat::from_float(1e10, 20).unwrap();
+        assert_eq!(
+            BigFloat::e(20).powf(&big).unwrap().to_string(),
+            "1.0777506079585649102e4342944819"
+        );
+        let ten = BigFloat::from_integer(&Integer::from(10), 20);
+        let scale = BigFloat::from_float(1.5, 20).unwrap();
+        assert_eq!(ten.powi(1000).mul(&scale).to_string(), "1.5e1000");
+        assert_eq!(one.div(&ten.powi(30)).neg().to_string(), "-1e-30");
+        assert_eq!(one.div(&ten.powi(3)).to_string(), "0.001");
+    }
+}
@@ -1,2 +1,4 @@
-pub type Integer = i128;
+pub type Integer = num_bigint::BigInt;
 pub type Float = f64;
+pub type Complex = num_complex::Complex<Float>;
+pub type Rational = num_rational::BigRational;
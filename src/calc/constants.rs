@@ -1,11 +1,27 @@
 use std::collections::HashMap;
+use crate::calc::bigfloat::BigFloat;
+use crate::calc::common::{Complex, Float};
 use crate::calc::value::Value;
 
 
-pub fn builtin_consts() -> HashMap<String, Value> {
+pub fn builtin_consts(precision: Option<u64>) -> HashMap<String, Value> {
     let mut consts: HashMap<String, Value> = HashMap::new();
-    consts.insert("pi".into(), Value::Float(std::f64::consts::PI));
-    consts.insert("e".into(), Value::Float(std::f64::consts::E));
-    consts.insert("phi".into(), Value::Float((1.0 + 5.0_f64.sqrt()) / 2.0));
-    return consts;
+    match precision {
+        Option::Some(prec) => {
+            consts.insert("pi".into(), Value::BigFloat(BigFloat::pi(prec)));
+            consts.insert("e".into(), Value::BigFloat(BigFloat::e(prec)));
+            consts.insert("phi".into(), Value::BigFloat(BigFloat::phi(prec)));
+        },
+        Option::None => {
+            consts.insert("pi".into(), Value::Float(std::f64::consts::PI));
+            consts.insert("e".into(), Value::Float(std::f64::consts::E));
+            consts.insert("phi".into(), Value::Float((1.0 + 5.0_f64.sqrt()) / 2.0));
+        },
+    }
+    consts.insert("inf".into(), Value::Float(Float::INFINITY));
+    consts.insert("true".into(), Value::Bool(true));
+    consts.insert("false".into(), Value::Bool(false));
+    consts.insert("i".into(), Value::Complex(Complex::new(0.0, 1.0)));
+    consts.insert("j".into(), Value::Complex(Complex::new(0.0, 1.0)));
+    consts
 }
@@ -1,10 +1,25 @@
+use crate::calc::format::Format;
 use crate::calc::functions::FuncHandle;
+use crate::calc::units::Unit;
 use crate::calc::value::Value;
 use std::collections::HashMap;
 
 
+#[derive(Clone, Default)]
 pub struct Context {
     pub consts: HashMap<String, Value>,
     pub funcs: HashMap<String, FuncHandle>,
+    pub units: HashMap<String, Unit>,
     pub allow_floating_bitwise_operations: bool,
+    pub rational_as_decimal: bool,
+    pub float_as_fraction: Option<u64>,
+    pub complex_as_polar: bool,
+    pub precision: Option<u64>,
+    pub promote_to_float: bool,
+    pub strict_float: bool,
+    pub int_width: Option<u32>,
+    pub unsigned: bool,
+    pub report_overflow: bool,
+    pub output_base: Option<u32>,
+    pub format: Format,
 }
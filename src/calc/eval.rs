@@ -1,6 +1,16 @@
+use crate::calc::bigfloat::BigFloat;
+use crate::calc::common::{Float, Rational};
 use crate::calc::context::Context;
+use crate::calc::functions;
 use crate::calc::nodes::{Atom, BinOp, Expr, UnOp};
+use crate::calc::polynomial;
+use crate::calc::quadrature;
+use crate::calc::rootfinding;
+use crate::calc::symbolic;
+use crate::calc::units::find_unit;
 use crate::calc::value::Value;
+use num_traits::{ToPrimitive, Zero};
+use std::cmp::Ordering;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum EvalErr {
@@ -9,6 +19,17 @@ pub enum EvalErr {
     ConstNotExists(String),
     InvalidArgument(String),
     InvalidFloatingPointOperation(String),
+    Overflow(String),
+    DivisionByZero(String),
+    NonFiniteResult(String),
+    DimensionMismatch(String),
+    UnitNotExists(String),
+    TypeMismatch(String),
+    LengthMismatch(String),
+    IndexOutOfRange(String),
+    ShapeMismatch(String),
+    SingularMatrix(String),
+    ToleranceNotMet(String),
 }
 
 pub type EvalResult = Result<Value, EvalErr>;
@@ -18,63 +39,581 @@ pub fn eval(ctx: &Context, expr: &Expr) -> EvalResult {
 }
 
 fn eval_expr(ctx: &Context, expr: &Expr) -> EvalResult {
-    match expr {
-        Expr::Atom(a) => Result::Ok(eval_atom(ctx, a)?),
-        Expr::UnOp(op, e) => Result::Ok(eval_unop(ctx, op, e)?),
-        Expr::BinOp(op, lhs, rhs) => Result::Ok(eval_binop(ctx, op, lhs, rhs)?),
+    let value = match expr {
+        Expr::Atom(a) => eval_atom(ctx, a)?,
+        Expr::UnOp(op, e) => eval_unop(ctx, op, e)?,
+        Expr::BinOp(op, lhs, rhs) => eval_binop(ctx, op, lhs, rhs)?,
+        Expr::Index(target, index) => eval_index(ctx, target, index)?,
+    };
+
+    if ctx.strict_float && !value.is_finite() {
+        let msg = format!("{} produced {}", expr, value);
+        return Result::Err(EvalErr::NonFiniteResult(msg));
     }
+    Result::Ok(value)
 }
 
 fn eval_unop(ctx: &Context, op: &UnOp, expr: &Expr) -> EvalResult {
     match op {
-        UnOp::Neg => Result::Ok(eval_expr(ctx, expr)?.neg()),
-        UnOp::Pos => Result::Ok(eval_expr(ctx, expr)?),
-        UnOp::Not => Result::Ok(eval_expr(ctx, expr)?.not(ctx)?),
+        // `-40 degC` negates the reading, not the temperature in kelvin
+        UnOp::Neg => match expr {
+            Expr::Atom(Atom::Quantity(value, name)) if is_absolute(ctx, expr) => {
+                eval_quantity(ctx, eval_atom(ctx, value)?.neg(ctx)?, name)
+            },
+            // checked after negation, so `-128` fits in 8 bits
+            Expr::Atom(Atom::Int(i)) => Value::checked_int(-i, ctx),
+            _ => Result::Ok(eval_expr(ctx, expr)?.into_si().neg(ctx)?),
+        },
+        UnOp::Pos => Result::Ok(eval_expr(ctx, expr)?.into_si()),
+        UnOp::Not => Result::Ok(eval_expr(ctx, expr)?.into_si().not(ctx)?),
     }
 }
 
 fn eval_binop(ctx: &Context, op: &BinOp, lhs: &Expr, rhs: &Expr) -> EvalResult {
-    let lvalue = eval_expr(ctx, lhs)?;
-    let rvalue = eval_expr(ctx, rhs)?;
+    match op {
+        BinOp::To => return eval_conversion(ctx, lhs, rhs),
+        BinOp::AndAnd => return eval_logical(ctx, lhs, rhs, false),
+        BinOp::OrOr => return eval_logical(ctx, lhs, rhs, true),
+        BinOp::Equation => {
+            return Result::Err(EvalErr::InvalidArgument(format!(
+                "equation {} = {} can only be used in solve", lhs, rhs
+            )))
+        },
+        _ => {},
+    }
+
+    // `10 degC` is stored in kelvin, so adding two such temperatures or
+    // scaling one would count the offset twice; a difference is fine
+    let (l, r) = (is_absolute(ctx, lhs), is_absolute(ctx, rhs));
+    let allowed = match op {
+        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => true,
+        BinOp::Plus => !(l && r),
+        BinOp::Minus => l || !r,
+        BinOp::PlusMinus => !r,
+        _ => !(l || r),
+    };
+    if !allowed {
+        let operand = if l { lhs } else { rhs };
+        return Result::Err(EvalErr::InvalidArgument(format!(
+            "{} is a temperature on an offset scale, which only supports conversion, comparison \
+             and differences, not {:?}", operand, op
+        )));
+    }
+
+    let lvalue = eval_expr(ctx, lhs)?.into_si();
+    let rvalue = match (op, ctx.int_width) {
+        // a shift count is a number of bits, not a fixed-width value
+        (BinOp::Shl | BinOp::Shr, Option::Some(_)) => {
+            eval_expr(&Context { int_width: Option::None, ..ctx.clone() }, rhs)?.into_si()
+        },
+        _ => eval_expr(ctx, rhs)?.into_si(),
+    };
 
     match op {
-        BinOp::Plus => Result::Ok(lvalue.add(rvalue)),
-        BinOp::Minus => Result::Ok(lvalue.sub(rvalue)),
-        BinOp::Mul => Result::Ok(lvalue.mul(rvalue)),
-        BinOp::Div => Result::Ok(lvalue.div(rvalue)),
-        BinOp::Mod => Result::Ok(lvalue.rem(rvalue)),
-        BinOp::Pow => Result::Ok(lvalue.pow(rvalue)),
+        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+            lvalue.broadcast(rvalue, &format!("{:?}", op), |a, b| eval_comparison(op, a, b))
+        },
+        BinOp::Plus => Result::Ok(lvalue.add(rvalue, ctx)?),
+        BinOp::Minus => Result::Ok(lvalue.sub(rvalue, ctx)?),
+        BinOp::Mul => Result::Ok(lvalue.mul(rvalue, ctx)?),
+        BinOp::Div => Result::Ok(lvalue.div(rvalue, ctx)?),
+        BinOp::Mod => Result::Ok(lvalue.rem(rvalue, ctx)?),
+        BinOp::Pow => Result::Ok(lvalue.pow(rvalue, ctx)?),
+        BinOp::PlusMinus => Result::Ok(lvalue.plus_minus(rvalue, ctx)?),
         BinOp::And => Result::Ok(lvalue.and(rvalue, ctx)?),
         BinOp::Or => Result::Ok(lvalue.or(rvalue, ctx)?),
         BinOp::Xor => Result::Ok(lvalue.xor(rvalue, ctx)?),
+        BinOp::Shl => Result::Ok(lvalue.shl(rvalue, ctx)?),
+        BinOp::Shr => Result::Ok(lvalue.shr(rvalue, ctx)?),
+        BinOp::To | BinOp::AndAnd | BinOp::OrOr | BinOp::Equation => unreachable!(),
+    }
+}
+
+// a temperature on a scale with an offset, such as `10 degC`, as opposed to
+// a difference in kelvin
+fn is_absolute(ctx: &Context, expr: &Expr) -> bool {
+    let affine = |name: &str| find_unit(&ctx.units, name).is_some_and(|unit| !unit.offset.is_zero());
+    match expr {
+        Expr::Atom(Atom::Quantity(_, name)) => find_const(ctx, name).is_none() && affine(name),
+        Expr::UnOp(_, e) => is_absolute(ctx, e),
+        Expr::BinOp(BinOp::To, _, rhs) => matches!(&**rhs, Expr::Atom(Atom::Const(name)) if affine(name)),
+        _ => false,
+    }
+}
+
+fn eval_comparison(op: &BinOp, lvalue: Value, rvalue: Value) -> EvalResult {
+    match op {
+        BinOp::Eq => Result::Ok(Value::Bool(lvalue.equals(&rvalue)?)),
+        BinOp::Ne => Result::Ok(Value::Bool(!lvalue.equals(&rvalue)?)),
+        _ => Result::Ok(Value::Bool(ordered(op, lvalue.compare(&rvalue)?))),
+    }
+}
+
+fn eval_index(ctx: &Context, target: &Expr, index: &Expr) -> EvalResult {
+    // indexing a matrix picks out a row
+    let items = match eval_expr(ctx, target)?.into_si() {
+        Value::Vector(items) => items,
+        Value::Matrix(m) => (0..m.rows()).map(|i| Value::Vector(m.row(i))).collect(),
+        v => return Result::Err(EvalErr::TypeMismatch(format!("only vectors can be indexed, got {}", v))),
+    };
+    let position = match eval_expr(ctx, index)?.into_si() {
+        Value::Int(i) => i,
+        v => return Result::Err(EvalErr::InvalidArgument(format!("index must be an integer, got {}", v))),
+    };
+    match position.to_usize().and_then(|i| items.get(i)) {
+        Option::Some(v) => Result::Ok(v.clone()),
+        Option::None => Result::Err(EvalErr::IndexOutOfRange(format!(
+            "index {} into {} items", position, items.len()
+        ))),
+    }
+}
+
+// `&&` and `||` skip their right operand once the left one decides the result
+fn eval_logical(ctx: &Context, lhs: &Expr, rhs: &Expr, short_circuit_on: bool) -> EvalResult {
+    if eval_condition(ctx, lhs)? == short_circuit_on {
+        return Result::Ok(Value::Bool(short_circuit_on));
+    }
+    Result::Ok(Value::Bool(eval_condition(ctx, rhs)?))
+}
+
+fn eval_condition(ctx: &Context, expr: &Expr) -> Result<bool, EvalErr> {
+    match eval_expr(ctx, expr)? {
+        Value::Bool(b) => Result::Ok(b),
+        v => Result::Err(EvalErr::TypeMismatch(format!("expected boolean condition, got {}", v))),
+    }
+}
+
+// only the chosen branch of `if(cond, a, b)` is evaluated
+fn eval_if(ctx: &Context, args: &[Expr]) -> EvalResult {
+    if args.len() != 3 {
+        let msg = format!("expected 3, got {}", args.len());
+        return Result::Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+    let branch = if eval_condition(ctx, &args[0])? { &args[1] } else { &args[2] };
+    eval_expr(ctx, branch)
+}
+
+// `diff(f, x)` gives the derivative of `f` as an expression; a third
+// argument evaluates it at that value of `x`
+fn eval_diff(ctx: &Context, args: &[Expr]) -> EvalResult {
+    if args.len() != 2 && args.len() != 3 {
+        let msg = format!("expected 2 or 3, got {}", args.len());
+        return Result::Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+    let var = symbolic::variable(ctx, &args[1])?;
+    let derivative = symbolic::derivative(&args[0], &var)?;
+    match args.get(2) {
+        Option::Some(point) => {
+            let x = eval_expr(ctx, point)?.into_si();
+            eval_at(ctx, &derivative, &var, x)
+        },
+        Option::None => Result::Ok(Value::Symbolic(Box::new(derivative))),
+    }
+}
+
+// `integrate(f, x, a, b)` integrates `f` numerically over `x` from `a` to
+// `b`, either of which may be infinite; a fifth argument sets the tolerance
+fn eval_integrate(ctx: &Context, args: &[Expr]) -> EvalResult {
+    if args.len() != 4 && args.len() != 5 {
+        let msg = format!("expected 4 or 5, got {}", args.len());
+        return Result::Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+    let var = symbolic::variable(ctx, &args[1])?;
+    let a = real(eval_expr(ctx, &args[2])?)?;
+    let b = real(eval_expr(ctx, &args[3])?)?;
+    let tolerance = match args.get(4) {
+        Option::Some(e) => match real(eval_expr(ctx, e)?)? {
+            t if t > 0.0 => t,
+            t => return Result::Err(EvalErr::InvalidArgument(format!("expected positive tolerance, got {}", t))),
+        },
+        Option::None => quadrature::DEFAULT_TOLERANCE,
+    };
+
+    let integrand = symbolic::with_variable(&args[0], &var);
+    let mut local = ctx.clone();
+    let value = quadrature::integrate(
+        |x| {
+            local.consts.insert(var.clone(), Value::Float(x));
+            real(eval_expr(&local, &integrand)?)
+        },
+        a,
+        b,
+        tolerance,
+    )?;
+    Result::Ok(Value::Float(value))
+}
+
+// `solve(f, x, guess)` finds a root of `f`, or of `lhs - rhs` for an
+// equation `lhs = rhs`, starting from `guess`; `solve(f, x, a, b)` looks
+// for one between `a` and `b`
+fn eval_solve(ctx: &Context, args: &[Expr]) -> EvalResult {
+    if args.len() != 3 && args.len() != 4 {
+        let msg = format!("expected 3 or 4, got {}", args.len());
+        return Result::Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+    let var = symbolic::variable(ctx, &args[1])?;
+    let f = symbolic::with_variable(&difference(&args[0]), &var);
+
+    let mut local = ctx.clone();
+    let mut value = |x| {
+        local.consts.insert(var.clone(), Value::Float(x));
+        real(eval_expr(&local, &f)?)
+    };
+    let root = if let Option::Some(b) = args.get(3) {
+        let a = real(eval_expr(ctx, &args[2])?)?;
+        let b = real(eval_expr(ctx, b)?)?;
+        rootfinding::between(&mut value, a, b)?
+    } else {
+        let guess = real(eval_expr(ctx, &args[2])?)?;
+        // the slope is estimated numerically for what cannot be differentiated
+        match symbolic::derivative(&f, &var) {
+            Result::Ok(df) => {
+                let mut local = ctx.clone();
+                let mut slope = |x| {
+                    local.consts.insert(var.clone(), Value::Float(x));
+                    real(eval_expr(&local, &df)?)
+                };
+                rootfinding::near(&mut value, Option::Some(&mut slope), guess)?
+            },
+            Result::Err(_) => rootfinding::near(&mut value, Option::None, guess)?,
+        }
+    };
+    Result::Ok(Value::Float(root))
+}
+
+// `roots(f, x)` takes its coefficients from the polynomial `f` in `x`;
+// otherwise the arguments are the coefficients themselves
+fn eval_roots(ctx: &Context, args: &[Expr]) -> EvalResult {
+    let var = match args {
+        [f, x] => symbolic::variable(ctx, x).ok().filter(|var| symbolic::depends_on(f, var)),
+        _ => Option::None,
+    };
+    let coefficients = match var {
+        Option::Some(var) => polynomial::coefficients(ctx, &difference(&args[0]), &var)?,
+        Option::None => args
+            .iter()
+            .map(|e| eval_expr(ctx, e).map(Value::into_si))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    functions::roots(&coefficients, ctx)
+}
+
+// an equation `lhs = rhs` as the expression `lhs - rhs`, which is zero
+// where it holds
+fn difference(expr: &Expr) -> Expr {
+    match expr {
+        Expr::BinOp(BinOp::Equation, lhs, rhs) => Expr::BinOp(BinOp::Minus, lhs.clone(), rhs.clone()),
+        e => e.clone(),
+    }
+}
+
+fn real(value: Value) -> Result<Float, EvalErr> {
+    match value.into_si() {
+        v @ (Value::Int(_) | Value::Float(_) | Value::Rational(_) | Value::BigFloat(_)) => Result::Ok(v.as_float()),
+        v => Result::Err(EvalErr::TypeMismatch(format!("expected real number, got {}", v))),
+    }
+}
+
+// evaluates `expr` with the variable `var` standing for `value`
+fn eval_at(ctx: &Context, expr: &Expr, var: &str, value: Value) -> EvalResult {
+    let mut local = ctx.clone();
+    local.consts.insert(var.to_lowercase(), value);
+    eval_expr(&local, expr)
+}
+
+// unordered operands, such as NaN, fail every ordering comparison
+fn ordered(op: &BinOp, ordering: Option<Ordering>) -> bool {
+    match (op, ordering) {
+        (_, Option::None) => false,
+        (BinOp::Lt, Option::Some(o)) => o == Ordering::Less,
+        (BinOp::Le, Option::Some(o)) => o != Ordering::Greater,
+        (BinOp::Gt, Option::Some(o)) => o == Ordering::Greater,
+        (BinOp::Ge, Option::Some(o)) => o != Ordering::Less,
+        _ => false,
+    }
+}
+
+fn eval_conversion(ctx: &Context, lhs: &Expr, rhs: &Expr) -> EvalResult {
+    let value = eval_expr(ctx, lhs)?.into_si();
+    if let Expr::Atom(Atom::Const(name)) = rhs {
+        if let Option::Some(base) = radix(name) {
+            return match value {
+                Value::Int(_) => Result::Ok(Value::Radix(Box::new(value), base)),
+                v => Result::Err(EvalErr::InvalidArgument(format!(
+                    "only integers can be shown in base {}, got {}", base, v
+                ))),
+            };
+        }
+    }
+
+    // a bare unit name may be an affine scale, which a product of units cannot
+    let (target, offset) = match rhs {
+        Expr::Atom(Atom::Const(name)) => match find_unit(&ctx.units, name) {
+            Option::Some(unit) => (Value::quantity(Value::from_ratio(unit.factor), unit.dim), unit.offset),
+            Option::None => (eval_expr(ctx, rhs)?.into_si(), Rational::zero()),
+        },
+        _ => (eval_expr(ctx, rhs)?.into_si(), Rational::zero()),
+    };
+
+    let unit = unit_text(rhs);
+    if target.dim().is_none() {
+        return Result::Err(EvalErr::InvalidArgument(format!("expected a unit, got {}", unit)));
+    }
+    convert(ctx, value, &target, &offset, unit)
+}
+
+fn convert(ctx: &Context, value: Value, target: &Value, offset: &Rational, unit: String) -> EvalResult {
+    if let Value::Vector(items) = value {
+        return Value::map_items(items, |v| convert(ctx, v, target, offset, unit.clone()));
+    }
+    if let Value::Matrix(m) = value {
+        return Result::Ok(Value::Matrix(m.try_map(|v| convert(ctx, v, target, offset, unit.clone()))?));
+    }
+    if value.dim() != target.dim() {
+        let msg = format!("cannot convert {} to {}", value, unit);
+        return Result::Err(EvalErr::DimensionMismatch(msg));
+    }
+
+    let mut magnitude = value.clone().magnitude();
+    if !offset.is_zero() {
+        magnitude = magnitude.sub(Value::from_ratio(offset.clone()), ctx)?;
+    }
+    let magnitude = magnitude.div(target.clone().magnitude(), ctx)?;
+    Result::Ok(Value::Converted(Box::new(magnitude), unit, Box::new(value)))
+}
+
+fn radix(name: &str) -> Option<u32> {
+    match name {
+        "bin" => Option::Some(2),
+        "oct" => Option::Some(8),
+        "dec" => Option::Some(10),
+        "hex" => Option::Some(16),
+        _ => Option::None,
+    }
+}
+
+fn unit_text(expr: &Expr) -> String {
+    match expr {
+        Expr::BinOp(BinOp::Mul, lhs, rhs) => format!("{}*{}", unit_text(lhs), unit_text(rhs)),
+        Expr::BinOp(BinOp::Div, lhs, rhs) => match **rhs {
+            Expr::BinOp(BinOp::Mul, ..) | Expr::BinOp(BinOp::Div, ..) => {
+                format!("{}/({})", unit_text(lhs), unit_text(rhs))
+            }
+            _ => format!("{}/{}", unit_text(lhs), unit_text(rhs)),
+        },
+        Expr::BinOp(BinOp::Pow, lhs, rhs) => format!("{}**{}", unit_text(lhs), unit_text(rhs)),
+        e => format!("{:?}", e),
     }
 }
 
 fn eval_atom(ctx: &Context, atom: &Atom) -> EvalResult {
     match atom {
-        Atom::Int(i) => Result::Ok(Value::Int(*i)),
-        Atom::Float(f) => Result::Ok(Value::Float(*f)),
+        Atom::Int(i) => Value::checked_int(i.clone(), ctx),
+        Atom::Float(f) => match ctx.precision.and_then(|p| BigFloat::from_float(*f, p)) {
+            Option::Some(b) => Result::Ok(Value::BigFloat(b)),
+            Option::None => Result::Ok(Value::Float(*f)),
+        },
         Atom::Const(name) => Result::Ok(eval_const(ctx, name.to_string())?),
+        // a name after a number is a unit unless it is a constant, as in `2 pi`
+        Atom::Unit(name) => match find_const(ctx, name) {
+            Option::Some(c) => Result::Ok(c.clone()),
+            Option::None => Result::Ok(eval_unit(ctx, name)?),
+        },
+        Atom::Quantity(value, name) => {
+            let value = eval_atom(ctx, value)?;
+            if let Option::Some(c) = find_const(ctx, name) {
+                return value.mul(c.clone(), ctx);
+            }
+            eval_quantity(ctx, value, name)
+        }
+        Atom::Vector(items) => {
+            let values: Vec<Value> = items
+                .iter()
+                .map(|e| eval_expr(ctx, e).map(Value::into_si))
+                .collect::<Result<Vec<_>, _>>()?;
+            Value::from_items(values)
+        },
+        Atom::Func(name, args) if name.eq_ignore_ascii_case("if") => eval_if(ctx, args),
+        Atom::Func(name, args) if name.eq_ignore_ascii_case("diff") => eval_diff(ctx, args),
+        Atom::Func(name, args) if name.eq_ignore_ascii_case("integrate") => eval_integrate(ctx, args),
+        Atom::Func(name, args) if name.eq_ignore_ascii_case("roots") => eval_roots(ctx, args),
+        // two arguments solve a linear system instead
+        Atom::Func(name, args) if name.eq_ignore_ascii_case("solve") && args.len() != 2 => eval_solve(ctx, args),
         Atom::Func(name, args) => {
             let values: Vec<Value> = args
                 .iter()
-                .map(|e| eval_expr(ctx, e))
+                .map(|e| eval_expr(ctx, e).map(Value::into_si))
                 .collect::<Result<Vec<_>, _>>()?;
             Result::Ok(eval_func(ctx, name.to_string(), &values)?)
         }
     }
 }
 
+fn eval_quantity(ctx: &Context, value: Value, name: &str) -> EvalResult {
+    let unit = find_unit(&ctx.units, name).ok_or(EvalErr::UnitNotExists(name.to_string()))?;
+    let mut si = value.mul(Value::from_ratio(unit.factor), ctx)?;
+    if !unit.offset.is_zero() {
+        si = si.add(Value::from_ratio(unit.offset), ctx)?;
+    }
+    Result::Ok(Value::quantity(si, unit.dim))
+}
+
 fn eval_const(ctx: &Context, name: String) -> EvalResult {
-    ctx.consts
-        .get(name.to_lowercase().as_str())
-        .cloned()
-        .ok_or_else(|| EvalErr::ConstNotExists(name))
+    if let Option::Some(value) = find_const(ctx, &name) {
+        return Result::Ok(value.clone());
+    }
+    eval_unit(ctx, &name).map_err(|_| EvalErr::ConstNotExists(name))
+}
+
+fn find_const<'a>(ctx: &'a Context, name: &str) -> Option<&'a Value> {
+    ctx.consts.get(name.to_lowercase().as_str())
+}
+
+fn eval_unit(ctx: &Context, name: &str) -> EvalResult {
+    match find_unit(&ctx.units, name) {
+        Option::Some(unit) => Result::Ok(Value::quantity(Value::from_ratio(unit.factor), unit.dim)),
+        Option::None => Result::Err(EvalErr::UnitNotExists(name.to_string())),
+    }
 }
 
 fn eval_func(ctx: &Context, name: String, args: &[Value]) -> EvalResult {
     ctx.funcs
         .get(name.to_lowercase().as_str())
         .ok_or_else(|| EvalErr::FuncNotExists(name.clone()))
-        .and_then(|f| f(args))
+        .and_then(|f| f(args, ctx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::common::Integer;
+    use crate::calc::constants::builtin_consts;
+    use crate::calc::functions::builtin_funcs;
+    use crate::calc::lexer::Lexer;
+    use crate::calc::parser::Parser;
+    use crate::calc::units::builtin_units;
+
+    fn context() -> Context {
+        Context {
+            consts: builtin_consts(Option::None),
+            funcs: builtin_funcs(),
+            units: builtin_units(),
+            ..Context::default()
+        }
+    }
+
+    fn calc_in(ctx: &Context, expr: &str) -> EvalResult {
+        eval(ctx, &Parser::new(Lexer::new(expr.to_string())).parse().unwrap())
+    }
+
+    fn calc(expr: &str) -> EvalResult {
+        calc_in(&context(), expr)
+    }
+
+    #[test]
+    fn gaussian_integral() {
+        // unary minus binds tighter than `**`, so the square is parenthesised
+        let area = calc("integrate(exp(-(x**2)), x, -inf, inf)").unwrap().as_float();
+        let root_pi = calc("sqrt(pi)").unwrap().as_float();
+        assert!((area - root_pi).abs() < 1e-12);
+        let slope = calc("diff(exp(x**2), x, 1)").unwrap().as_float();
+        assert!((slope - 2.0 * std::f64::consts::E).abs() < 1e-12);
+    }
+
+    #[test]
+    fn lazy_conditionals() {
+        let result = |expr: &str| calc(expr).unwrap().to_string();
+        // the branch not taken is never evaluated, errors included
+        assert_eq!(result("if(1 < 2, 3, 1/0)"), "3");
+        assert_eq!(result("if(1 > 2, nosuch(1), 4)"), "4");
+        assert_eq!(result("1 > 2 && nosuch(1)"), "false");
+        assert_eq!(result("1 < 2 || nosuch(1)"), "true");
+        assert_eq!(result("1 < 2 && 2 < 3"), "true");
+        assert!(matches!(calc("1 < 2 && nosuch(1)"), Result::Err(EvalErr::FuncNotExists(_))));
+        assert!(matches!(calc("if(1, 2, 3)"), Result::Err(EvalErr::TypeMismatch(_))));
+        assert!(matches!(calc("1 || 2 < 3"), Result::Err(EvalErr::TypeMismatch(_))));
+        assert!(matches!(calc("if(1 < 2, 3)"), Result::Err(EvalErr::IncorrectArgumentCount(_))));
+    }
+
+    #[test]
+    fn vectors() {
+        let result = |expr: &str| calc(expr).unwrap().to_string();
+        assert_eq!(result("[1, 2, 3] + 1"), "[2, 3, 4]");
+        assert_eq!(result("2 ** [1, 2, 3]"), "[2, 4, 8]");
+        assert_eq!(result("[1, 2, 3] * [4, 5, 6]"), "[4, 10, 18]");
+        assert_eq!(result("-[1, 2]"), "[-1, -2]");
+        assert_eq!(result("[1, 2] < [2, 1]"), "[true, false]");
+        assert!(matches!(calc("[1, 2] + [1, 2, 3]"), Result::Err(EvalErr::LengthMismatch(_))));
+
+        assert_eq!(result("[10, 20, 30][0]"), "10");
+        assert_eq!(result("[10, 20, 30][1 + 1]"), "30");
+        assert_eq!(result("[[1, 2], [3, 4]][1][0]"), "3");
+        assert!(matches!(calc("[10, 20, 30][3]"), Result::Err(EvalErr::IndexOutOfRange(_))));
+        assert!(matches!(calc("[10, 20, 30][-1]"), Result::Err(EvalErr::IndexOutOfRange(_))));
+        assert!(matches!(calc("[10, 20, 30][1.5]"), Result::Err(EvalErr::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn fixed_width_integers() {
+        let ctx = Context { int_width: Option::Some(8), ..context() };
+        let result = |expr: &str| calc_in(&ctx, expr).unwrap().to_string();
+        assert_eq!(result("200"), "-56");
+        assert_eq!(result("127 + 1"), "-128");
+        assert_eq!(result("-128"), "-128");
+        assert_eq!(result("~0"), "-1");
+        assert_eq!(result("16 * 16"), "0");
+        // shift counts are plain numbers of bits however they are written
+        assert_eq!(result("1 << 4"), result("1 << (2 + 2)"));
+        assert_eq!(result("1 << (100 + 100)"), "0");
+
+        let unsigned = Context { unsigned: true, ..ctx.clone() };
+        assert_eq!(calc_in(&unsigned, "-1").unwrap().to_string(), "255");
+        assert_eq!(calc_in(&unsigned, "~0").unwrap().to_string(), "255");
+        assert_eq!(calc_in(&unsigned, "0 - 1").unwrap().to_string(), "255");
+
+        let one_bit = Context { int_width: Option::Some(1), ..ctx.clone() };
+        assert_eq!(calc_in(&one_bit, "-1 >> 1").unwrap().to_string(), "-1");
+    }
+
+    #[test]
+    fn fixed_width_overflow_reports() {
+        let ctx = Context { int_width: Option::Some(8), report_overflow: true, ..context() };
+        let overflows = |ctx: &Context, expr: &str| matches!(calc_in(ctx, expr), Result::Err(EvalErr::Overflow(_)));
+        assert!(overflows(&ctx, "200"));
+        assert!(overflows(&ctx, "-129"));
+        assert!(overflows(&ctx, "100 + 100"));
+        assert!(overflows(&ctx, "2 ** 7"));
+        assert_eq!(calc_in(&ctx, "-128").unwrap().to_string(), "-128");
+        assert_eq!(calc_in(&ctx, "~0").unwrap().to_string(), "-1");
+
+        let unsigned = Context { unsigned: true, ..ctx };
+        assert!(overflows(&unsigned, "-1"));
+        assert!(overflows(&unsigned, "256"));
+        assert_eq!(calc_in(&unsigned, "255").unwrap().to_string(), "255");
+    }
+
+    #[test]
+    fn radix_round_trip() {
+        for n in [0, 1, 255, -255, 1i64 << 40] {
+            for (suffix, base) in [("hex", 16), ("oct", 8), ("bin", 2)] {
+                let shown = calc(&format!("{} to {}", n, suffix)).unwrap().to_string();
+                assert_eq!(shown, Value::Int(Integer::from(n)).fmt_radix(base, Option::None));
+                assert_eq!(calc(&shown).unwrap().as_int(), Integer::from(n));
+            }
+        }
+        assert_eq!(calc("255 to hex").unwrap().to_string(), "0xff");
+        assert_eq!(calc("-5 to bin").unwrap().to_string(), "-0b101");
+        assert!(calc("1.5 to hex").is_err());
+
+        // --output-base writes every integer in a result
+        let items = calc("[10, -11]").unwrap();
+        assert_eq!(items.fmt_radix(16, Option::None), "[0xa, -0xb]");
+        assert_eq!(Value::Float(2.5).fmt_radix(16, Option::None), "2.5");
+
+        // a fixed width shows the two's complement pattern, which reads back
+        let byte = Context { int_width: Option::Some(8), ..context() };
+        let shown = calc_in(&byte, "-2").unwrap().fmt_radix(2, byte.int_width);
+        assert_eq!(shown, "0b11111110");
+        assert_eq!(calc_in(&byte, &shown).unwrap().to_string(), "-2");
+    }
 }
@@ -0,0 +1,197 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use num_traits::{Signed, Zero};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Notation {
+    #[default]
+    Plain,
+    Scientific,
+    Engineering,
+}
+
+// significant figures are written without an exponent between these powers
+// of ten; the lower bound is the one printf's %g uses
+const MIN_PLAIN_EXPONENT: i64 = -4;
+const MAX_PLAIN_EXPONENT: i64 = 15;
+
+// how the numbers in a result are written out
+#[derive(Clone, Default, PartialEq)]
+pub struct Format {
+    pub notation: Notation,
+    pub decimals: Option<usize>,
+    pub significant: Option<usize>,
+    pub separators: bool,
+}
+
+impl Format {
+    // rewrites a decimal literal such as "-1234.5" or "1.5e-7"; infinities
+    // and NaN are left alone
+    pub fn decimal(&self, s: &str) -> String {
+        self.decimal_rounded(s, RoundingMode::HalfUp)
+    }
+
+    // as `decimal`, rounding the dropped digits the given way, so the ends
+    // of an interval can be moved outwards
+    pub fn decimal_rounded(&self, s: &str, mode: RoundingMode) -> String {
+        match BigDecimal::from_str(s) {
+            Result::Ok(value) => self.number_rounded(&value, mode),
+            Result::Err(_) => s.to_string(),
+        }
+    }
+
+    pub fn number(&self, value: &BigDecimal) -> String {
+        self.number_rounded(value, RoundingMode::HalfUp)
+    }
+
+    fn number_rounded(&self, value: &BigDecimal, mode: RoundingMode) -> String {
+        match self.notation {
+            Notation::Plain => self.plain(value, mode),
+            Notation::Scientific => self.exponential(value, 1, mode),
+            Notation::Engineering => self.exponential(value, 3, mode),
+        }
+    }
+
+    fn plain(&self, value: &BigDecimal, mode: RoundingMode) -> String {
+        match (self.decimals, self.significant) {
+            (Option::Some(d), _) => fixed_point(&round(value, d as i64, mode), self.separators),
+            (Option::None, Option::Some(n)) => {
+                let exp = exponent(value);
+                if !(MIN_PLAIN_EXPONENT..=MAX_PLAIN_EXPONENT).contains(&exp) {
+                    return self.exponential(value, 1, mode);
+                }
+                // the zeros asked for are kept, as in 2.50 to three figures
+                let mut rounded = round(value, n as i64 - 1 - exp, mode);
+                if exponent(&rounded) != exp {
+                    rounded = round(&rounded, n as i64 - 1 - exponent(&rounded), mode);
+                }
+                fixed_point(&rounded, self.separators)
+            },
+            (Option::None, Option::None) => trim_zeros(fixed_point(value, self.separators)),
+        }
+    }
+
+    // a mantissa times a power of ten that is a multiple of `step`
+    fn exponential(&self, value: &BigDecimal, step: i64, mode: RoundingMode) -> String {
+        let mut exp = exponent(value);
+        loop {
+            let shift = exp.div_euclid(step) * step;
+            let whole_digits = exp - shift + 1;
+            let decimals = match (self.decimals, self.significant) {
+                (Option::Some(d), _) => d as i64,
+                (Option::None, Option::Some(n)) => (n as i64 - whole_digits).max(0),
+                (Option::None, Option::None) => (value.normalized().digits() as i64 - whole_digits).max(0),
+            };
+            let rounded = round(value, decimals - shift, mode);
+            // rounding may carry into a new leading digit, as 9.99 does to 10.0
+            if exponent(&rounded) != exp {
+                exp = exponent(&rounded);
+                continue;
+            }
+            let (digits, scale) = rounded.into_bigint_and_exponent();
+            let mantissa = fixed_point(&BigDecimal::new(digits, scale + shift), false);
+            let exact = self.decimals.is_none() && self.significant.is_none();
+            let mantissa = if exact { trim_zeros(mantissa) } else { mantissa };
+            return format!("{}e{}", mantissa, shift);
+        }
+    }
+}
+
+fn round(value: &BigDecimal, scale: i64, mode: RoundingMode) -> BigDecimal {
+    value.with_scale_round(scale, mode)
+}
+
+// the power of ten of the leading digit
+pub fn exponent(value: &BigDecimal) -> i64 {
+    if value.is_zero() {
+        return 0;
+    }
+    let (_, scale) = value.as_bigint_and_exponent();
+    value.digits() as i64 - 1 - scale
+}
+
+fn fixed_point(value: &BigDecimal, separators: bool) -> String {
+    let (digits, scale) = value.as_bigint_and_exponent();
+    let sign = if digits.is_negative() { "-" } else { "" };
+    let mut text = digits.abs().to_string();
+    let fraction = if scale > 0 {
+        let scale = scale as usize;
+        if text.len() <= scale {
+            text = format!("{}{}", "0".repeat(scale + 1 - text.len()), text);
+        }
+        text.split_off(text.len() - scale)
+    } else {
+        text.push_str(&"0".repeat(scale.unsigned_abs() as usize));
+        String::new()
+    };
+    if separators {
+        text = group_thousands(&text);
+    }
+    if fraction.is_empty() {
+        return format!("{}{}", sign, text);
+    }
+    format!("{}{}.{}", sign, text, fraction)
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+fn trim_zeros(text: String) -> String {
+    if !text.contains('.') {
+        return text;
+    }
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(notation: Notation, decimals: Option<usize>, significant: Option<usize>) -> Format {
+        Format { notation, decimals, significant, separators: false }
+    }
+
+    #[test]
+    fn plain_rounding() {
+        assert_eq!(format(Notation::Plain, Option::Some(2), Option::None).decimal("3.14159"), "3.14");
+        assert_eq!(format(Notation::Plain, Option::Some(2), Option::None).decimal("5"), "5.00");
+        assert_eq!(format(Notation::Plain, Option::None, Option::Some(3)).decimal("0.30000000000000004"), "0.300");
+        assert_eq!(format(Notation::Plain, Option::None, Option::Some(2)).decimal("123456"), "120000");
+        let grouped = Format { separators: true, ..Format::default() };
+        assert_eq!(grouped.decimal("-1234567.891"), "-1,234,567.891");
+        assert_eq!(grouped.decimal("inf"), "inf");
+    }
+
+    #[test]
+    fn exponential_notation() {
+        assert_eq!(format(Notation::Scientific, Option::None, Option::None).decimal("1200"), "1.2e3");
+        assert_eq!(format(Notation::Scientific, Option::Some(2), Option::None).decimal("0.000123"), "1.23e-4");
+        assert_eq!(format(Notation::Engineering, Option::None, Option::None).decimal("0.0047"), "4.7e-3");
+        assert_eq!(format(Notation::Engineering, Option::None, Option::Some(4)).decimal("999.96"), "1.000e3");
+        assert_eq!(format(Notation::Engineering, Option::None, Option::None).decimal("-47000"), "-47e3");
+    }
+
+    #[test]
+    fn significant_figures() {
+        let sig = |n| format(Notation::Plain, Option::None, Option::Some(n));
+        // requested zeros are significant
+        assert_eq!(sig(3).decimal("2.5"), "2.50");
+        assert_eq!(sig(3).decimal("9.996"), "10.0");
+        // far from 1 the digits move into an exponent
+        assert_eq!(sig(3).decimal("1e-310"), "1.00e-310");
+        assert_eq!(sig(3).decimal("0.0001234"), "0.000123");
+        assert_eq!(sig(3).decimal("0.00001234"), "1.23e-5");
+        assert_eq!(sig(3).decimal("1180591620717411303424"), "1.18e21");
+        let fixed = format(Notation::Plain, Option::Some(0), Option::None);
+        assert_eq!(fixed.decimal_rounded("1.5", RoundingMode::Floor), "1");
+        assert_eq!(fixed.decimal_rounded("1.5", RoundingMode::Ceiling), "2");
+    }
+}
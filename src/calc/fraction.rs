@@ -0,0 +1,95 @@
+use crate::calc::common::{Float, Integer, Rational};
+use num_integer::Integer as _;
+use num_traits::{One, Signed, Zero};
+
+// denominator limit used by `frac` and the fraction output mode by default
+pub const DEFAULT_MAX_DENOMINATOR: u64 = 1000;
+
+// how close, relative to the value, a fraction must be to stand in for a float
+const TOLERANCE: Float = 1e-9;
+
+// the successive convergents of the continued fraction of `x` whose
+// denominators do not exceed `max_den`
+pub fn convergents(x: &Rational, max_den: &Integer) -> Vec<Rational> {
+    let mut result = Vec::new();
+    expand(x, max_den, |p, q| result.push(Rational::new(p.clone(), q.clone())));
+    result
+}
+
+// the fraction closest to `x` among those with a denominator of at most
+// `max_den`, which is either the last convergent or a semiconvergent
+pub fn best_approximation(x: &Rational, max_den: &Integer) -> Rational {
+    if x.denom() <= max_den {
+        return x.clone();
+    }
+    let (mut p0, mut q0, mut p1, mut q1) = (Integer::zero(), Integer::one(), Integer::one(), Integer::zero());
+    expand(x, max_den, |p, q| {
+        p0 = std::mem::replace(&mut p1, p.clone());
+        q0 = std::mem::replace(&mut q1, q.clone());
+    });
+    let k = (max_den - &q0) / &q1;
+    let semi = Rational::new(&p0 + &k * &p1, &q0 + &k * &q1);
+    let last = Rational::new(p1, q1);
+    if (&semi - x).abs() < (&last - x).abs() {
+        return semi;
+    }
+    last
+}
+
+// a fraction with a small denominator that `x` is a rounded form of, if any
+pub fn nearby(x: Float, max_den: &Integer) -> Option<Rational> {
+    let exact = Rational::from_float(x)?;
+    let approx = best_approximation(&exact, max_den);
+    if (&approx - &exact).abs() > Rational::from_float(TOLERANCE * x.abs())? {
+        return Option::None;
+    }
+    Option::Some(approx)
+}
+
+// runs the continued fraction expansion of `x`, calling `f` with each
+// convergent until the next denominator would exceed `max_den`
+fn expand<F>(x: &Rational, max_den: &Integer, mut f: F)
+where
+    F: FnMut(&Integer, &Integer),
+{
+    let (mut n, mut d) = (x.numer().clone(), x.denom().clone());
+    let (mut p0, mut q0, mut p1, mut q1) = (Integer::zero(), Integer::one(), Integer::one(), Integer::zero());
+    while !d.is_zero() {
+        let (a, r) = n.div_mod_floor(&d);
+        let q2 = &q0 + &a * &q1;
+        if &q2 > max_den {
+            break;
+        }
+        let p2 = &p0 + &a * &p1;
+        f(&p2, &q2);
+        (p0, q0, p1, q1) = (p1, q1, p2, q2);
+        (n, d) = (d, r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(n: i64, d: i64) -> Rational {
+        Rational::new(Integer::from(n), Integer::from(d))
+    }
+
+    #[test]
+    fn continued_fractions() {
+        let pi = Rational::from_float(std::f64::consts::PI).unwrap();
+        let limit = Integer::from(1000);
+        assert_eq!(convergents(&pi, &limit), vec![ratio(3, 1), ratio(22, 7), ratio(333, 106), ratio(355, 113)]);
+        assert_eq!(best_approximation(&pi, &Integer::from(100)), ratio(311, 99));
+        assert_eq!(best_approximation(&ratio(-10, 3), &Integer::from(2)), ratio(-7, 2));
+    }
+
+    #[test]
+    fn nearby_fractions() {
+        let limit = Integer::from(DEFAULT_MAX_DENOMINATOR);
+        assert_eq!(nearby(0.3333333333, &limit), Option::Some(ratio(1, 3)));
+        assert_eq!(nearby(0.1 + 0.2, &limit), Option::Some(ratio(3, 10)));
+        assert_eq!(nearby(std::f64::consts::PI, &limit), Option::None);
+        assert_eq!(nearby(Float::NAN, &limit), Option::None);
+    }
+}
@@ -1,13 +1,25 @@
 use std::collections::HashMap;
 
 
-use crate::calc::common::{Integer, Float};
+use crate::calc::bigfloat::BigFloat;
+use crate::calc::common::{Complex, Integer, Float, Rational};
+use crate::calc::context::Context;
 use crate::calc::eval::{EvalResult, EvalErr};
-use crate::calc::value::Value;
+use crate::calc::fraction::{self, best_approximation, DEFAULT_MAX_DENOMINATOR};
+use crate::calc::interval::Interval;
+use crate::calc::matrix::{Matrix, MAX_MATRIX_ITEMS};
+use crate::calc::polynomial;
+use crate::calc::statistics::{self, Moments};
+use crate::calc::uncertain::Uncertain;
+use crate::calc::units::Dimension;
+use crate::calc::value::{Value, MAX_INTEGER_BITS};
+use num_integer::Integer as _;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
 
 
 pub type FuncArg = [Value];
-pub type FuncHandle = fn (&FuncArg) -> EvalResult;
+pub type FuncHandle = fn (&FuncArg, &Context) -> EvalResult;
 
 
 pub fn builtin_funcs() -> HashMap<String, FuncHandle> {
@@ -18,58 +30,157 @@ pub fn builtin_funcs() -> HashMap<String, FuncHandle> {
     hashmap.insert("min".into(), min);
     hashmap.insert("max".into(), max);
     hashmap.insert("avg".into(), avg);
+    hashmap.insert("median".into(), median);
+    hashmap.insert("mode".into(), mode);
+    hashmap.insert("var".into(), var);
+    hashmap.insert("pvar".into(), pvar);
+    hashmap.insert("stddev".into(), stddev);
+    hashmap.insert("pstddev".into(), pstddev);
+    hashmap.insert("percentile".into(), percentile);
+    hashmap.insert("quantile".into(), quantile);
+    hashmap.insert("geomean".into(), geomean);
+    hashmap.insert("harmean".into(), harmean);
+    hashmap.insert("prod".into(), prod);
+    hashmap.insert("range".into(), range);
+    hashmap.insert("skew".into(), skew);
+    hashmap.insert("kurtosis".into(), kurtosis);
     hashmap.insert("ceil".into(), ceil);
     hashmap.insert("floor".into(), floor);
     hashmap.insert("log".into(), log);
+    hashmap.insert("exp".into(), exp);
     hashmap.insert("deg".into(), deg);
     hashmap.insert("rad".into(), rad);
     hashmap.insert("fact".into(), fact);
     hashmap.insert("ncr".into(), ncr);
     hashmap.insert("npr".into(), npr);
-    return hashmap;
+    hashmap.insert("sqrt".into(), sqrt);
+    hashmap.insert("abs".into(), abs);
+    hashmap.insert("re".into(), re);
+    hashmap.insert("im".into(), im);
+    hashmap.insert("arg".into(), arg);
+    hashmap.insert("conj".into(), conj);
+    hashmap.insert("iv".into(), iv);
+    hashmap.insert("frac".into(), frac);
+    hashmap.insert("convergents".into(), convergents);
+    hashmap.insert("dot".into(), dot);
+    hashmap.insert("cross".into(), cross);
+    hashmap.insert("norm".into(), norm);
+    hashmap.insert("len".into(), len);
+    hashmap.insert("sum".into(), sum);
+    hashmap.insert("det".into(), det);
+    hashmap.insert("inv".into(), inv);
+    hashmap.insert("transpose".into(), transpose);
+    hashmap.insert("rank".into(), rank);
+    hashmap.insert("trace".into(), trace);
+    hashmap.insert("identity".into(), identity);
+    hashmap.insert("solve".into(), solve);
+    hashmap.insert("roots".into(), roots);
+    hashmap.insert("polyval".into(), polyval);
+    hashmap.insert("polyfit".into(), polyfit);
+    hashmap.insert("polymul".into(), polymul);
+    hashmap.insert("rotl".into(), rotl);
+    hashmap.insert("rotr".into(), rotr);
+    hashmap.insert("popcount".into(), popcount);
+    hashmap.insert("clz".into(), clz);
+    hashmap.insert("ctz".into(), ctz);
+    hashmap.insert("bit".into(), bit);
+    hashmap
 }
 
-pub fn sin(args: &FuncArg) -> EvalResult {
+pub fn sin(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1, got {}", args.len());
         return Result::Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    let x = &(args[0]);
-    Result::Ok(Value::auto(x.as_float().sin()))
+    dimensionless(args)?;
+
+    if let Value::Interval(x) = &args[0] {
+        return Result::Ok(Value::Interval(x.sin()));
+    }
+
+    if let Value::Uncertain(x) = &args[0] {
+        return Result::Ok(Value::Uncertain(x.map(Float::sin, Float::cos)));
+    }
+
+    if let Option::Some(x) = precise(ctx, &args[0]) {
+        let msg = format!("{} is too large an angle to reduce", x);
+        return x.sin().map(Value::BigFloat).ok_or(EvalErr::Overflow(msg));
+    }
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::from_complex(c.sin())),
+        x => Result::Ok(Value::auto(x.as_float().sin())),
+    }
 }
 
-pub fn cos(args: &FuncArg) -> EvalResult {
+pub fn cos(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1, got {}", args.len());
         return Result::Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    let x = &(args[0]);
-    Result::Ok(Value::auto(x.as_float().cos()))
+    dimensionless(args)?;
+
+    if let Value::Interval(x) = &args[0] {
+        return Result::Ok(Value::Interval(x.cos()));
+    }
+
+    if let Value::Uncertain(x) = &args[0] {
+        return Result::Ok(Value::Uncertain(x.map(Float::cos, |x| -x.sin())));
+    }
+
+    if let Option::Some(x) = precise(ctx, &args[0]) {
+        let msg = format!("{} is too large an angle to reduce", x);
+        return x.cos().map(Value::BigFloat).ok_or(EvalErr::Overflow(msg));
+    }
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::from_complex(c.cos())),
+        x => Result::Ok(Value::auto(x.as_float().cos())),
+    }
 }
 
-pub fn tan(args: &FuncArg) -> EvalResult {
+pub fn tan(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1, got {}", args.len());
         return Result::Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    let x = &(args[0]);
-    Result::Ok(Value::auto(x.as_float().tan()))
+    dimensionless(args)?;
+
+    if let Value::Interval(x) = &args[0] {
+        let msg = format!("tan is unbounded on {}", x);
+        return x.tan().map(Value::Interval).ok_or(EvalErr::InvalidArgument(msg));
+    }
+
+    if let Value::Uncertain(x) = &args[0] {
+        return Result::Ok(Value::Uncertain(x.map(Float::tan, |x| 1.0 / x.cos().powi(2))));
+    }
+
+    if let Option::Some(x) = precise(ctx, &args[0]) {
+        let msg = format!("{} is too large an angle to reduce", x);
+        return x.tan().map(Value::BigFloat).ok_or(EvalErr::Overflow(msg));
+    }
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::from_complex(c.tan())),
+        x => Result::Ok(Value::auto(x.as_float().tan())),
+    }
 }
 
-pub fn min(args: &FuncArg) -> EvalResult {
+pub fn min(args: &FuncArg, _ctx: &Context) -> EvalResult {
     if args.len() < 2 {
         let msg = format!("expected at least 2 values, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
+    common_dim(args)?;
     let mut min_val = &args[0];
-    let mut min_float = args[0].as_float();
+    let mut min_float = centre(&args[0])?;
 
     for v in args.iter().skip(1) {
-        let f = v.as_float();
+        let f = centre(v)?;
         if f < min_float {
             min_float = f;
             min_val = v;
@@ -79,17 +190,18 @@ pub fn min(args: &FuncArg) -> EvalResult {
     Result::Ok((*min_val).clone())
 }
 
-pub fn max(args: &FuncArg) -> EvalResult {
+pub fn max(args: &FuncArg, _ctx: &Context) -> EvalResult {
     if args.len() < 2 {
         let msg = format!("expected at least 2 values, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
+    common_dim(args)?;
     let mut max_val = &args[0];
-    let mut max_float = args[0].as_float();
+    let mut max_float = centre(&args[0])?;
 
     for v in args.iter().skip(1) {
-        let f = v.as_float();
+        let f = centre(v)?;
         if f > max_float {
             max_float = f;
             max_val = v;
@@ -99,136 +211,1310 @@ pub fn max(args: &FuncArg) -> EvalResult {
     Result::Ok((*max_val).clone())
 }
 
-pub fn avg(args: &FuncArg) -> EvalResult {
+pub fn avg(args: &FuncArg, ctx: &Context) -> EvalResult {
+    let args = samples(args);
     if args.is_empty() {
         return Err(EvalErr::IncorrectArgumentCount(
             "expected at least 1 value".into(),
         ));
     }
 
-    let sum: Float = args.iter().map(|v| v.as_float()).sum();
+    numbers(args)?;
+    let dim = common_dim(args)?;
+    if !dim.is_none() {
+        let magnitudes: Vec<Value> = args.iter().map(|v| v.clone().magnitude()).collect();
+        return Result::Ok(Value::quantity(avg(&magnitudes, ctx)?, dim));
+    }
+
+    if args.iter().any(|v| v.is_interval()) {
+        let mut sum = Interval::point(0.0);
+        for v in args {
+            match v.as_interval() {
+                Option::Some(x) => sum = sum.add(&x),
+                Option::None => return Result::Err(EvalErr::InvalidArgument(
+                    format!("expected real number or interval, got {}", v),
+                )),
+            }
+        }
+        let count = Interval::point(args.len() as Float);
+        return Result::Ok(Value::Interval(sum.div(&count).unwrap_or(sum)));
+    }
+
+    if args.iter().any(|v| v.is_uncertain()) {
+        let mut sum = Uncertain::exact(0.0);
+        for v in args {
+            match v.as_uncertain() {
+                Option::Some(x) => sum = sum.add(&x),
+                Option::None => return Result::Err(EvalErr::InvalidArgument(
+                    format!("expected real number or uncertain value, got {}", v),
+                )),
+            }
+        }
+        return Result::Ok(Value::Uncertain(sum.scale(1.0 / args.len() as Float)));
+    }
+
+    if let Option::Some(values) = precise_all(ctx, args) {
+        let prec = values[0].prec();
+        let sum = values
+            .iter()
+            .fold(BigFloat::from_integer(&Integer::zero(), prec), |acc, v| acc.add(v));
+        let count = BigFloat::from_integer(&Integer::from(args.len()), prec);
+        return Result::Ok(Value::BigFloat(sum.div(&count)));
+    }
+
+    if args.iter().any(|v| v.is_complex()) {
+        let re = statistics::sum(args.iter().map(|v| v.as_complex().re));
+        let im = statistics::sum(args.iter().map(|v| v.as_complex().im));
+        return Result::Ok(Value::from_complex(Complex::new(re, im) / args.len() as Float));
+    }
+
+    let sum = statistics::sum(args.iter().map(Value::as_float));
     let avg = sum / args.len() as Float;
     Result::Ok(Value::auto(avg))
 }
 
-pub fn ceil(args: &FuncArg) -> EvalResult {
+pub fn median(args: &FuncArg, ctx: &Context) -> EvalResult {
+    let half = Value::Rational(Rational::new(Integer::one(), Integer::from(2)));
+    quantile_of(samples(args), &half, ctx)
+}
+
+// the most common value, or the smallest of those tied for it
+pub fn mode(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    let items = sorted(samples(args))?;
+    let (mut best, mut best_count) = (0, 0);
+    let mut i = 0;
+    while i < items.len() {
+        let mut j = i + 1;
+        while j < items.len() && items[j].equals(&items[i])? {
+            j += 1;
+        }
+        if j - i > best_count {
+            (best, best_count) = (i, j - i);
+        }
+        i = j;
+    }
+    Result::Ok(items[best].clone())
+}
+
+// the sample variance, divided by `n - 1`
+pub fn var(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    Result::Ok(Value::auto(moments(args, 2)?.variance(true)))
+}
+
+// the population variance, divided by `n`
+pub fn pvar(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    Result::Ok(Value::auto(moments(args, 1)?.variance(false)))
+}
+
+pub fn stddev(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    Result::Ok(Value::auto(moments(args, 2)?.variance(true).sqrt()))
+}
+
+pub fn pstddev(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    Result::Ok(Value::auto(moments(args, 1)?.variance(false).sqrt()))
+}
+
+// `percentile(p, x1, ..., xn)` for `p` from 0 to 100
+pub fn percentile(args: &FuncArg, ctx: &Context) -> EvalResult {
+    let Option::Some((p, data)) = args.split_first() else {
+        let msg = "expected a percentile and values, got nothing".to_string();
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    };
+
+    if !(0.0..=100.0).contains(&real(p)?) {
+        let msg = format!("expected percentile from 0 to 100, got {}", p);
+        return Result::Err(EvalErr::InvalidArgument(msg));
+    }
+    let q = p.clone().div(Value::Int(Integer::from(100)), ctx)?;
+    quantile_of(samples(data), &q, ctx)
+}
+
+// `quantile(q, x1, ..., xn)` for `q` from 0 to 1
+pub fn quantile(args: &FuncArg, ctx: &Context) -> EvalResult {
+    let Option::Some((q, data)) = args.split_first() else {
+        let msg = "expected a quantile and values, got nothing".to_string();
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    };
+
+    if !(0.0..=1.0).contains(&real(q)?) {
+        let msg = format!("expected quantile from 0 to 1, got {}", q);
+        return Result::Err(EvalErr::InvalidArgument(msg));
+    }
+    quantile_of(samples(data), q, ctx)
+}
+
+pub fn geomean(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    let xs = non_negative(args)?;
+    if xs.contains(&0.0) {
+        return Result::Ok(Value::zero());
+    }
+    let log_sum = statistics::sum(xs.iter().map(|x| x.ln()));
+    Result::Ok(Value::auto((log_sum / xs.len() as Float).exp()))
+}
+
+pub fn harmean(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    let xs = non_negative(args)?;
+    if xs.contains(&0.0) {
+        return Result::Ok(Value::zero());
+    }
+    let reciprocal_sum = statistics::sum(xs.iter().map(|x| 1.0 / x));
+    Result::Ok(Value::auto(xs.len() as Float / reciprocal_sum))
+}
+
+pub fn prod(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.is_empty() {
+        let msg = "expected at least 1 value, got 0".to_string();
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let items = samples(args);
+    let mut total = items.first().cloned().unwrap_or(Value::Int(Integer::one()));
+    for v in items.iter().skip(1) {
+        total = total.mul(v.clone(), ctx)?;
+    }
+    Result::Ok(total)
+}
+
+// the difference between the largest and smallest values
+pub fn range(args: &FuncArg, ctx: &Context) -> EvalResult {
+    let items = sorted(samples(args))?;
+    match (items.first(), items.last()) {
+        (Option::Some(lo), Option::Some(hi)) => hi.clone().sub(lo.clone(), ctx),
+        _ => Err(EvalErr::IncorrectArgumentCount("expected at least 1 value, got 0".to_string())),
+    }
+}
+
+pub fn skew(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    Result::Ok(Value::auto(spread(moments(args, 1)?)?.skewness()))
+}
+
+// the excess kurtosis, zero for a normal distribution
+pub fn kurtosis(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    Result::Ok(Value::auto(spread(moments(args, 1)?)?.kurtosis()))
+}
+
+pub fn ceil(args: &FuncArg, _ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1 value, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    Result::Ok(Value::auto(args[0].as_float().ceil()))
+    dimensionless(args)?;
+
+    match &args[0] {
+        Value::BigFloat(b) => Result::Ok(Value::Int(b.ceil())),
+        // a step function has zero derivative, so the spread vanishes to first order
+        Value::Uncertain(u) => Result::Ok(Value::auto(u.value().ceil())),
+        x => Result::Ok(Value::auto(real(x)?.ceil())),
+    }
 }
 
-pub fn floor(args: &FuncArg) -> EvalResult {
+pub fn floor(args: &FuncArg, _ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1 value, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    Result::Ok(Value::auto(args[0].as_float().floor()))
+    dimensionless(args)?;
+
+    match &args[0] {
+        Value::BigFloat(b) => Result::Ok(Value::Int(b.floor())),
+        // a step function has zero derivative, so the spread vanishes to first order
+        Value::Uncertain(u) => Result::Ok(Value::auto(u.value().floor())),
+        x => Result::Ok(Value::auto(real(x)?.floor())),
+    }
 }
 
-pub fn log(args: &FuncArg) -> EvalResult {
+pub fn log(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 2 {
         let msg = format!("expected 2, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    let base = args[0].as_float();
-    let value = args[1].as_float();
+    dimensionless(args)?;
+
+    if args[0].is_uncertain() || args[1].is_uncertain() {
+        let (b, x) = match (args[0].as_uncertain(), args[1].as_uncertain()) {
+            (Option::Some(b), Option::Some(x)) => (b, x),
+            _ => return Result::Err(EvalErr::InvalidArgument(format!(
+                "expected real numbers, got {} and {}", args[0], args[1]
+            ))),
+        };
+        if b.value() <= 0.0 || b.value() == 1.0 || x.value() <= 0.0 {
+            return Err(EvalErr::InvalidArgument(
+                "log base must be > 0 and != 1 and value must be > 0".into(),
+            ));
+        }
+        let ln_b = b.map(Float::ln, Float::recip);
+        let ln_x = x.map(Float::ln, Float::recip);
+        // the base is not 1, so its logarithm is never zero
+        return Result::Ok(Value::Uncertain(ln_x.div(&ln_b).unwrap_or(ln_x)));
+    }
+
+    let base = real(&args[0])?;
 
     if base <= 0.0 || base == 1.0 {
         return Err(EvalErr::InvalidArgument(
             "log base must be > 0 and != 1".into(),
         ));
     }
-    if value <= 0.0 {
-        return Err(EvalErr::InvalidArgument("log value must be > 0".into()));
+
+    if let Value::Interval(x) = &args[1] {
+        let ln_base = Interval::around(base.ln());
+        let msg = format!("log value must be > 0, got {}", x);
+        let ln = x.ln().ok_or(EvalErr::InvalidArgument(msg))?;
+        return Result::Ok(Value::Interval(ln.div(&ln_base).unwrap_or(ln)));
     }
 
-    Result::Ok(Value::auto(value.log(base)))
+    if let (Option::Some(b), Option::Some(v)) = (precise(ctx, &args[0]), precise(ctx, &args[1])) {
+        if let (Option::Some(ln_b), Option::Some(ln_v)) = (b.ln(), v.ln()) {
+            return Result::Ok(Value::BigFloat(ln_v.div(&ln_b)));
+        }
+    }
+
+    let value = args[1].as_complex();
+    if value.norm() == 0.0 {
+        return Err(EvalErr::InvalidArgument("log value must be non-zero".into()));
+    }
+    if value.im == 0.0 && value.re > 0.0 {
+        return Result::Ok(Value::auto(value.re.log(base)));
+    }
+
+    Result::Ok(Value::from_complex(value.ln() / base.ln()))
 }
 
-pub fn deg(args: &FuncArg) -> EvalResult {
+pub fn exp(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1, got {}", args.len());
+        return Result::Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    dimensionless(args)?;
+
+    if let Value::Interval(x) = &args[0] {
+        return Result::Ok(Value::Interval(x.exp()));
+    }
+
+    if let Value::Uncertain(x) = &args[0] {
+        return Result::Ok(Value::Uncertain(x.map(Float::exp, Float::exp)));
+    }
+
+    if let Option::Some(x) = precise(ctx, &args[0]) {
+        let msg = format!("exp({}) is out of the precise exponent range", x);
+        return x.exp().map(Value::BigFloat).ok_or(EvalErr::Overflow(msg));
+    }
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::from_complex(c.exp())),
+        x => Result::Ok(Value::auto(x.as_float().exp())),
+    }
+}
+
+pub fn deg(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1 value, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    Result::Ok(Value::auto(args[0].as_float().to_degrees()))
+    dimensionless(args)?;
+
+    if let Value::Uncertain(x) = &args[0] {
+        return Result::Ok(Value::Uncertain(x.scale(1.0_f64.to_degrees())));
+    }
+
+    if let Option::Some(x) = precise(ctx, &args[0]) {
+        let half_turn = BigFloat::from_integer(&Integer::from(180), x.prec());
+        return Result::Ok(Value::BigFloat(x.mul(&half_turn).div(&BigFloat::pi(x.prec()))));
+    }
+
+    Result::Ok(Value::auto(real(&args[0])?.to_degrees()))
 }
 
-pub fn rad(args: &FuncArg) -> EvalResult {
+pub fn rad(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1 value, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    Result::Ok(Value::auto(args[0].as_float().to_radians()))
+    dimensionless(args)?;
+
+    if let Value::Uncertain(x) = &args[0] {
+        return Result::Ok(Value::Uncertain(x.scale(1.0_f64.to_radians())));
+    }
+
+    if let Option::Some(x) = precise(ctx, &args[0]) {
+        let half_turn = BigFloat::from_integer(&Integer::from(180), x.prec());
+        return Result::Ok(Value::BigFloat(x.mul(&BigFloat::pi(x.prec())).div(&half_turn)));
+    }
+
+    Result::Ok(Value::auto(real(&args[0])?.to_radians()))
 }
 
-pub fn fact(args: &FuncArg) -> EvalResult {
+pub fn fact(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 1 {
         let msg = format!("expected 1 value, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
-    match args[0] {
+    match &args[0] {
         Value::Int(i) => {
-            if i < 0 {
+            if i.is_negative() {
                 let msg = format!("expected non-negative integer, got {}", i);
                 return Result::Err(EvalErr::InvalidArgument(msg));
-            } else if i == 0 {
-                return Result::Ok(Value::Int(1 as Integer));
             }
-            return Result::Ok(Value::Int((0..i).product()));
+            if factorial_overflows(i) {
+                return Value::overflow(ctx, Float::INFINITY);
+            }
+            let mut result = Integer::one();
+            let mut k = Integer::one();
+            while &k <= i {
+                result *= &k;
+                k += 1;
+            }
+            Result::Ok(Value::Int(result))
         },
-        Value::Float(f) => {
-            let msg = format!("expected non-negative integer, got {}", f);
-            return Result::Err(EvalErr::InvalidArgument(msg));
+        v => {
+            let msg = format!("expected non-negative integer, got {}", v);
+            Result::Err(EvalErr::InvalidArgument(msg))
         },
     }
 }
 
-pub fn ncr(args: &FuncArg) -> EvalResult {
+// n! takes floor(log2(n!)) + 1 bits
+fn factorial_overflows(n: &Integer) -> bool {
+    match n.to_u64() {
+        Option::Some(n) => log2_factorial(n) >= MAX_INTEGER_BITS as Float,
+        Option::None => true,
+    }
+}
+
+// Stirling's series, which is off by far less than a bit
+fn log2_factorial(n: u64) -> Float {
+    if n < 2 {
+        return 0.0;
+    }
+    let n = n as Float;
+    let ln = n * n.ln() - n + 0.5 * (std::f64::consts::TAU * n).ln() + 1.0 / (12.0 * n);
+    ln / std::f64::consts::LN_2
+}
+
+pub fn ncr(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 2 {
         let msg = format!("expected 2, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
+    real(&args[0])?;
+    real(&args[1])?;
     let n = args[0].as_int();
-    let r = args[1].as_int();
+    let r = selection_size(&args[1])?;
     if n < r {
         return Result::Ok(Value::zero());
     }
 
-    let r = r.min(n-r);
-    let mut result = 1;
+    // n choose r is at least (n / r)^r
+    let r = r.clone().min(&n - &r);
+    if log2_selections(&n, &r, &r) >= MAX_INTEGER_BITS as Float {
+        return Value::overflow(ctx, Float::INFINITY);
+    }
+    let mut result = Integer::one();
+    let mut i = Integer::zero();
 
-    for i in 0..r {
-        result = result * (n-i) / (i+1);
+    while i < r {
+        result = result * (&n - &i) / (&i + 1);
+        i += 1;
     }
 
-    Result::Ok(Value::Int(result as Integer))
+    Result::Ok(Value::Int(result))
 }
 
-pub fn npr(args: &FuncArg) -> EvalResult {
+pub fn npr(args: &FuncArg, ctx: &Context) -> EvalResult {
     if args.len() != 2 {
         let msg = format!("expected 2, got {}", args.len());
         return Err(EvalErr::IncorrectArgumentCount(msg));
     }
 
+    real(&args[0])?;
+    real(&args[1])?;
     let n = args[0].as_int();
-    let r = args[1].as_int();
+    let r = selection_size(&args[1])?;
     if n < r {
         return Result::Ok(Value::zero());
     }
 
-    let mut result = 1;
-    for i in 0..r {
-        result = n-i;
+    // n! / (n - r)! is at least (n - r + 1)^r
+    if log2_selections(&(&n - &r + 1), &Integer::one(), &r) >= MAX_INTEGER_BITS as Float {
+        return Value::overflow(ctx, Float::INFINITY);
+    }
+
+    let mut result = Integer::one();
+    let mut i = Integer::zero();
+    while i < r {
+        result *= &n - &i;
+        i += 1;
+    }
+
+    Result::Ok(Value::Int(result))
+}
+
+fn selection_size(v: &Value) -> Result<Integer, EvalErr> {
+    let r = v.as_int();
+    if r.is_negative() {
+        let msg = format!("expected non-negative integer, got {}", v);
+        return Result::Err(EvalErr::InvalidArgument(msg));
+    }
+    Result::Ok(r)
+}
+
+// log2 of (n / d)^r, a lower bound on the bits of a selection count
+fn log2_selections(n: &Integer, d: &Integer, r: &Integer) -> Float {
+    if r.is_zero() {
+        return 0.0;
+    }
+    let r = r.to_f64().unwrap_or(Float::INFINITY);
+    r * (log2(n) - log2(d)).max(0.0)
+}
+
+// keeps the top 64 bits so integers beyond the float range still have a log
+fn log2(n: &Integer) -> Float {
+    let shift = n.bits().saturating_sub(64);
+    (n >> shift).to_f64().unwrap_or(Float::NAN).log2() + shift as Float
+}
+
+pub fn sqrt(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 value, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    numbers(args)?;
+
+    if let Value::Quantity(v, dim) = &args[0] {
+        let msg = format!("sqrt of {} has fractional dimensions", args[0]);
+        let root = dim.root(2).ok_or(EvalErr::DimensionMismatch(msg))?;
+        return Result::Ok(Value::quantity(sqrt(&[(**v).clone()], ctx)?, root));
+    }
+
+    if let Value::Interval(x) = &args[0] {
+        let msg = format!("sqrt of interval {} must be non-negative", x);
+        return x.sqrt().map(Value::Interval).ok_or(EvalErr::InvalidArgument(msg));
+    }
+
+    if let Value::Uncertain(x) = &args[0] {
+        if x.value() < 0.0 {
+            let msg = format!("sqrt of uncertain value {} must be non-negative", x);
+            return Result::Err(EvalErr::InvalidArgument(msg));
+        }
+        return Result::Ok(Value::Uncertain(x.map(Float::sqrt, |x| 0.5 / x.sqrt())));
+    }
+
+    if let Option::Some(root) = precise(ctx, &args[0]).and_then(|x| x.sqrt()) {
+        return Result::Ok(Value::BigFloat(root));
+    }
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::from_complex(c.sqrt())),
+        x if x.as_float() < 0.0 => Result::Ok(Value::from_complex(x.as_complex().sqrt())),
+        x => Result::Ok(Value::auto(x.as_float().sqrt())),
+    }
+}
+
+pub fn abs(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 value, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    match &args[0] {
+        Value::Bool(_) => Result::Err(EvalErr::TypeMismatch(format!("expected number, got {}", args[0]))),
+        // abs(-128) does not fit in 8 bits
+        Value::Int(i) => Value::checked_int(i.abs(), ctx),
+        Value::Float(f) => Result::Ok(Value::Float(f.abs())),
+        Value::Rational(r) => Result::Ok(Value::Rational(r.abs())),
+        Value::Complex(c) => Result::Ok(Value::auto(c.norm())),
+        Value::BigFloat(b) => Result::Ok(Value::BigFloat(b.abs())),
+        Value::Interval(x) => Result::Ok(Value::Interval(x.abs())),
+        Value::Uncertain(x) => Result::Ok(Value::Uncertain(x.map(Float::abs, Float::signum))),
+        Value::Quantity(v, dim) => Result::Ok(Value::Quantity(Box::new(abs(&[(**v).clone()], ctx)?), *dim)),
+        Value::Converted(_, _, si) => abs(&[(**si).clone()], ctx),
+        Value::Radix(v, _) => abs(&[(**v).clone()], ctx),
+        Value::Vector(items) => Value::map_items(items.clone(), |v| abs(&[v], ctx)),
+        Value::Matrix(m) => Result::Ok(Value::Matrix(m.clone().try_map(|v| abs(&[v], ctx))?)),
+        Value::Symbolic(_) => Result::Err(EvalErr::TypeMismatch(format!("expected number, got {}", args[0]))),
+    }
+}
+
+pub fn re(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 value, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::Float(c.re)),
+        x => Result::Ok(x.clone()),
+    }
+}
+
+pub fn im(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 value, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    dimensionless(args)?;
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::Float(c.im)),
+        _ => Result::Ok(Value::zero()),
+    }
+}
+
+pub fn arg(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 value, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    dimensionless(args)?;
+
+    Result::Ok(Value::auto(args[0].as_complex().arg()))
+}
+
+pub fn conj(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 value, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    match &args[0] {
+        Value::Complex(c) => Result::Ok(Value::Complex(c.conj())),
+        x => Result::Ok(x.clone()),
+    }
+}
+
+pub fn iv(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 2 {
+        let msg = format!("expected 2 values, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    real(&args[0])?;
+    real(&args[1])?;
+    let lo = args[0].as_interval().map(|x| x.lo()).unwrap_or(Float::NAN);
+    let hi = args[1].as_interval().map(|x| x.hi()).unwrap_or(Float::NAN);
+    match Interval::new(lo, hi) {
+        Option::Some(x) => Result::Ok(Value::Interval(x)),
+        Option::None => {
+            let msg = format!("expected lower bound <= upper bound, got {} and {}", args[0], args[1]);
+            Result::Err(EvalErr::InvalidArgument(msg))
+        },
+    }
+}
+
+pub fn frac(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 && args.len() != 2 {
+        let msg = format!("expected 1 or 2, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let (x, max_den) = fraction_args(args)?;
+    Result::Ok(Value::from_ratio(best_approximation(&x, &max_den)))
+}
+
+pub fn convergents(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 && args.len() != 2 {
+        let msg = format!("expected 1 or 2, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let (x, max_den) = fraction_args(args)?;
+    let items = fraction::convergents(&x, &max_den).into_iter().map(Value::from_ratio).collect();
+    Result::Ok(Value::Vector(items))
+}
+
+pub fn dot(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 {
+        let msg = format!("expected 2 vectors, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let (a, b) = (vector(&args[0])?, vector(&args[1])?);
+    if a.len() != b.len() {
+        let msg = format!("dot of {} and {}", args[0], args[1]);
+        return Result::Err(EvalErr::LengthMismatch(msg));
     }
 
-    Result::Ok(Value::Int(result as Integer))
+    let products = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| x.clone().mul(y.clone(), ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+    sum(&[Value::Vector(products)], ctx)
+}
+
+pub fn cross(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 {
+        let msg = format!("expected 2 vectors, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let (a, b) = (vector(&args[0])?, vector(&args[1])?);
+    if a.len() != 3 || b.len() != 3 {
+        let msg = format!("cross product needs vectors of length 3, got {} and {}", args[0], args[1]);
+        return Result::Err(EvalErr::LengthMismatch(msg));
+    }
+
+    let term = |i: usize, j: usize| -> EvalResult {
+        let lhs = a[i].clone().mul(b[j].clone(), ctx)?;
+        lhs.sub(a[j].clone().mul(b[i].clone(), ctx)?, ctx)
+    };
+    Result::Ok(Value::Vector(vec![term(1, 2)?, term(2, 0)?, term(0, 1)?]))
+}
+
+pub fn norm(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 vector, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let squares = abs(args, ctx)?;
+    sqrt(&[dot(&[squares.clone(), squares], ctx)?], ctx)
+}
+
+pub fn len(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 vector, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    Result::Ok(Value::Int(Integer::from(vector(&args[0])?.len())))
+}
+
+pub fn sum(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.is_empty() {
+        let msg = "expected at least 1 value, got 0".to_string();
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let items = samples(args);
+    // floats lose the least to a compensated sum
+    let real = |v: &Value| matches!(v, Value::Int(_) | Value::Float(_) | Value::Rational(_));
+    if items.iter().any(|v| matches!(v, Value::Float(_))) && items.iter().all(real) {
+        return Result::Ok(Value::Float(statistics::sum(items.iter().map(Value::as_float))));
+    }
+
+    // seeding with the first element keeps the dimension of quantities
+    let mut total = items.first().cloned().unwrap_or_else(Value::zero);
+    for v in items.iter().skip(1) {
+        total = total.add(v.clone(), ctx)?;
+    }
+    Result::Ok(total)
+}
+
+pub fn det(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 matrix, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    matrix(&args[0])?.det(ctx)
+}
+
+pub fn inv(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 matrix, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    Result::Ok(Value::Matrix(matrix(&args[0])?.inverse(ctx)?))
+}
+
+pub fn transpose(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 matrix, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    Result::Ok(Value::Matrix(matrix(&args[0])?.transpose()))
+}
+
+pub fn rank(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 matrix, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    Result::Ok(Value::Int(Integer::from(matrix(&args[0])?.rank(ctx)?)))
+}
+
+pub fn trace(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 matrix, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    matrix(&args[0])?.trace(ctx)
+}
+
+pub fn identity(args: &FuncArg, _ctx: &Context) -> EvalResult {
+    if args.len() != 1 {
+        let msg = format!("expected 1 value, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    match &args[0] {
+        Value::Int(n) if n.is_positive() => match n.to_usize().filter(|n| n.saturating_mul(*n) <= MAX_MATRIX_ITEMS) {
+            Option::Some(n) => Result::Ok(Value::Matrix(Matrix::identity(n))),
+            Option::None => {
+                let msg = format!("identity matrix exceeds {} items", MAX_MATRIX_ITEMS);
+                Result::Err(EvalErr::Overflow(msg))
+            },
+        },
+        v => {
+            let msg = format!("expected positive integer, got {}", v);
+            Result::Err(EvalErr::InvalidArgument(msg))
+        },
+    }
+}
+
+// solves `A * x = b` for a vector or matrix `b`
+pub fn solve(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 {
+        let msg = format!("expected 2 values, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let a = matrix(&args[0])?;
+    match &args[1] {
+        Value::Matrix(b) => Result::Ok(Value::Matrix(a.solve(b, ctx)?)),
+        Value::Vector(b) => {
+            let msg = format!("expected non-empty vector, got {}", args[1]);
+            let b = Matrix::column(b.clone()).ok_or(EvalErr::ShapeMismatch(msg))?;
+            Result::Ok(Value::Vector(a.solve(&b, ctx)?.into_items()))
+        },
+        v => Result::Err(EvalErr::TypeMismatch(format!("expected vector or matrix, got {}", v))),
+    }
+}
+
+// every root of the polynomial with coefficients `a_n, ..., a_0`, given
+// one by one or as a vector
+pub fn roots(args: &FuncArg, ctx: &Context) -> EvalResult {
+    let coefficients = match args {
+        [] => {
+            let msg = "expected at least 1 coefficient, got 0".to_string();
+            return Err(EvalErr::IncorrectArgumentCount(msg));
+        },
+        [Value::Vector(items)] => items.as_slice(),
+        _ => args,
+    };
+
+    Result::Ok(Value::Vector(polynomial::roots(coefficients, ctx)?))
+}
+
+// the polynomial with coefficients `p` at `x`, or at each item of a vector
+pub fn polyval(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 {
+        let msg = format!("expected 2 values, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let p = vector(&args[0])?;
+    match &args[1] {
+        Value::Vector(xs) => Value::map_items(xs.clone(), |x| polynomial::evaluate(p, &x, ctx)),
+        x => polynomial::evaluate(p, x, ctx),
+    }
+}
+
+pub fn polyfit(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 3 {
+        let msg = format!("expected 3 values, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let degree = match &args[2] {
+        Value::Int(n) => n.to_usize(),
+        _ => Option::None,
+    };
+    let Option::Some(degree) = degree else {
+        let msg = format!("expected non-negative integer degree, got {}", args[2]);
+        return Result::Err(EvalErr::InvalidArgument(msg));
+    };
+    let (xs, ys) = (vector(&args[0])?, vector(&args[1])?);
+    Result::Ok(Value::Vector(polynomial::fit(xs, ys, degree, ctx)?))
+}
+
+pub fn polymul(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 {
+        let msg = format!("expected 2 vectors, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let (a, b) = (vector(&args[0])?, vector(&args[1])?);
+    Result::Ok(Value::Vector(polynomial::multiply(a, b, ctx)?))
+}
+
+pub fn rotl(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 && args.len() != 3 {
+        let msg = format!("expected 2 or 3, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let width = bit_width(args, 2, ctx)?;
+    let (x, n) = (bits_of(&args[0], ctx)?, bits_of(&args[1], ctx)?);
+    Result::Ok(Value::Int(Value::wrap(rotate_left(&x, &n, width), ctx)))
+}
+
+pub fn rotr(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 && args.len() != 3 {
+        let msg = format!("expected 2 or 3, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let width = bit_width(args, 2, ctx)?;
+    let (x, n) = (bits_of(&args[0], ctx)?, bits_of(&args[1], ctx)?);
+    Result::Ok(Value::Int(Value::wrap(rotate_left(&x, &-n, width), ctx)))
+}
+
+pub fn popcount(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 && args.len() != 2 {
+        let msg = format!("expected 1 or 2, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let x = bits_of(&args[0], ctx)?;
+    // without a width only non-negative numbers have finitely many ones
+    let pattern = if args.len() == 2 || ctx.int_width.is_some() || x.is_negative() {
+        bit_pattern(&x, bit_width(args, 1, ctx)?)
+    } else {
+        x
+    };
+    Result::Ok(Value::Int(Integer::from(pattern.magnitude().count_ones())))
+}
+
+pub fn clz(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 && args.len() != 2 {
+        let msg = format!("expected 1 or 2, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let width = bit_width(args, 1, ctx)?;
+    let pattern = bit_pattern(&bits_of(&args[0], ctx)?, width);
+    Result::Ok(Value::Int(Integer::from(width as u64 - pattern.bits())))
+}
+
+pub fn ctz(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 1 && args.len() != 2 {
+        let msg = format!("expected 1 or 2, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let x = bits_of(&args[0], ctx)?;
+    // zero has as many trailing zeros as its width allows
+    let n = match x.trailing_zeros() {
+        Option::Some(n) if args.len() == 1 && ctx.int_width.is_none() => n,
+        _ => {
+            let width = bit_width(args, 1, ctx)?;
+            bit_pattern(&x, width).trailing_zeros().unwrap_or(width as u64)
+        },
+    };
+    Result::Ok(Value::Int(Integer::from(n)))
+}
+
+pub fn bit(args: &FuncArg, ctx: &Context) -> EvalResult {
+    if args.len() != 2 {
+        let msg = format!("expected 2, got {}", args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    let (x, n) = (bits_of(&args[0], ctx)?, bits_of(&args[1], ctx)?);
+    if n.is_negative() {
+        let msg = format!("expected non-negative bit position, got {}", n);
+        return Result::Err(EvalErr::InvalidArgument(msg));
+    }
+    match n.to_u64() {
+        Option::Some(n) => Result::Ok(Value::Int(Integer::from(x.bit(n) as u8))),
+        Option::None => {
+            let msg = format!("bit position {} is out of range", n);
+            Result::Err(EvalErr::IndexOutOfRange(msg))
+        },
+    }
+}
+
+fn rotate_left(x: &Integer, n: &Integer, width: u32) -> Integer {
+    let pattern = bit_pattern(x, width);
+    let k = n.mod_floor(&Integer::from(width)).to_u32().unwrap_or_default();
+    bit_pattern(&((&pattern << k) | (&pattern >> (width - k))), width)
+}
+
+fn bit_pattern(x: &Integer, width: u32) -> Integer {
+    x.mod_floor(&(Integer::one() << width))
+}
+
+// an explicit width argument, falling back to the one set by --int-width
+fn bit_width(args: &FuncArg, index: usize, ctx: &Context) -> Result<u32, EvalErr> {
+    match args.get(index) {
+        Option::Some(v) => match v {
+            Value::Int(w) => w.to_u32().filter(|w| (1..=4096).contains(w)),
+            _ => Option::None,
+        }
+        .ok_or(EvalErr::InvalidArgument(format!("expected a width from 1 to 4096, got {}", v))),
+        Option::None => ctx.int_width.ok_or(EvalErr::InvalidArgument(
+            "expected a width argument or --int-width".to_string(),
+        )),
+    }
+}
+
+fn bits_of(v: &Value, ctx: &Context) -> Result<Integer, EvalErr> {
+    if let Value::Int(i) = v {
+        return Result::Ok(i.clone());
+    }
+    real(v)?;
+    if ctx.allow_floating_bitwise_operations {
+        return Result::Ok(v.as_int());
+    }
+    Result::Err(EvalErr::InvalidFloatingPointOperation(
+        "Bitwise operator cannot be operated on floating point numbers".to_string()
+    ))
+}
+
+// the number to approximate and the largest denominator allowed
+fn fraction_args(args: &FuncArg) -> Result<(Rational, Integer), EvalErr> {
+    let x = real(&args[0])?;
+    let x = args[0].as_ratio().or_else(|| Rational::from_float(x)).ok_or_else(|| {
+        EvalErr::InvalidArgument(format!("expected finite number, got {}", args[0]))
+    })?;
+    let max_den = match args.get(1) {
+        Option::None => Integer::from(DEFAULT_MAX_DENOMINATOR),
+        Option::Some(Value::Int(n)) if n.is_positive() => n.clone(),
+        Option::Some(v) => {
+            let msg = format!("expected positive integer denominator limit, got {}", v);
+            return Result::Err(EvalErr::InvalidArgument(msg));
+        },
+    };
+    Result::Ok((x, max_den))
+}
+
+// a lone vector argument stands for its items
+fn samples(args: &FuncArg) -> &FuncArg {
+    match args {
+        [Value::Vector(items)] => items,
+        _ => args,
+    }
+}
+
+// the moments of at least `min` real numbers
+fn moments(args: &FuncArg, min: usize) -> Result<Moments, EvalErr> {
+    let args = samples(args);
+    if args.len() < min {
+        let msg = format!("expected at least {} values, got {}", min, args.len());
+        return Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+    let xs = args.iter().map(real).collect::<Result<Vec<_>, _>>()?;
+    Result::Ok(Moments::of(&xs))
+}
+
+// the shape of values that are all the same is undefined
+fn spread(m: Moments) -> Result<Moments, EvalErr> {
+    if m.variance(false) == 0.0 {
+        let msg = "expected values that are not all equal".to_string();
+        return Result::Err(EvalErr::InvalidArgument(msg));
+    }
+    Result::Ok(m)
+}
+
+fn non_negative(args: &FuncArg) -> Result<Vec<Float>, EvalErr> {
+    let args = samples(args);
+    if args.is_empty() {
+        return Err(EvalErr::IncorrectArgumentCount("expected at least 1 value, got 0".to_string()));
+    }
+    let xs = args.iter().map(real).collect::<Result<Vec<_>, _>>()?;
+    match xs.iter().position(|&x| x < 0.0) {
+        Option::Some(i) => Result::Err(EvalErr::InvalidArgument(format!(
+            "expected non-negative values, got {}", args[i]
+        ))),
+        Option::None => Result::Ok(xs),
+    }
+}
+
+// from smallest to largest, failing for values that have no order
+fn sorted(args: &FuncArg) -> Result<Vec<Value>, EvalErr> {
+    if args.is_empty() {
+        return Err(EvalErr::IncorrectArgumentCount("expected at least 1 value, got 0".to_string()));
+    }
+    numbers(args)?;
+    common_dim(args)?;
+    let mut items = args.to_vec();
+    let mut error = Option::None;
+    items.sort_by(|a, b| match a.compare(b) {
+        Result::Ok(Option::Some(o)) => o,
+        Result::Ok(Option::None) => {
+            error.get_or_insert_with(|| EvalErr::InvalidArgument(format!("{} and {} cannot be ordered", a, b)));
+            Ordering::Equal
+        },
+        Result::Err(e) => {
+            error.get_or_insert(e);
+            Ordering::Equal
+        },
+    });
+    match error {
+        Option::Some(e) => Result::Err(e),
+        Option::None => Result::Ok(items),
+    }
+}
+
+// linear interpolation between the two nearest ranks, the default of
+// spreadsheets and numpy; exact values at an exact level give an exact result
+fn quantile_of(data: &FuncArg, q: &Value, ctx: &Context) -> EvalResult {
+    let items = sorted(data)?;
+    let position = q.clone().mul(Value::Int(Integer::from(items.len() - 1)), ctx)?;
+    let (index, fraction) = match position.as_ratio() {
+        Option::Some(r) => (r.floor().to_integer().to_usize(), Value::from_ratio(r.fract())),
+        Option::None => (position.as_float().floor().to_usize(), Value::Float(position.as_float().fract())),
+    };
+    let index = index.unwrap_or_default().min(items.len() - 1);
+    let low = items[index].clone();
+    if fraction.is_zero() || index + 1 == items.len() {
+        return Result::Ok(low);
+    }
+    let step = items[index + 1].clone().sub(low.clone(), ctx)?.mul(fraction, ctx)?;
+    low.add(step, ctx)
+}
+
+fn matrix(v: &Value) -> Result<&Matrix, EvalErr> {
+    match v {
+        Value::Matrix(m) => Result::Ok(m),
+        v => Result::Err(EvalErr::TypeMismatch(format!("expected matrix, got {}", v))),
+    }
+}
+
+fn vector(v: &Value) -> Result<&Vec<Value>, EvalErr> {
+    match v {
+        Value::Vector(items) => Result::Ok(items),
+        v => Result::Err(EvalErr::TypeMismatch(format!("expected vector, got {}", v))),
+    }
+}
+
+fn real(v: &Value) -> Result<Float, EvalErr> {
+    if v.is_bool() || v.is_array() || v.is_symbolic() {
+        let msg = format!("expected number, got {}", v);
+        return Result::Err(EvalErr::TypeMismatch(msg));
+    }
+    if v.is_quantity() {
+        let msg = format!("expected dimensionless number, got {}", v);
+        return Result::Err(EvalErr::DimensionMismatch(msg));
+    }
+    if v.is_complex() || v.is_interval() || v.is_uncertain() {
+        let msg = format!("expected real number, got {}", v);
+        return Result::Err(EvalErr::InvalidArgument(msg));
+    }
+    Result::Ok(v.as_float())
+}
+
+// orders uncertain values by their central value and quantities by magnitude
+fn centre(v: &Value) -> Result<Float, EvalErr> {
+    match v {
+        Value::Uncertain(u) => Result::Ok(u.value()),
+        Value::Quantity(v, _) => centre(v),
+        v => real(v),
+    }
+}
+
+fn numbers(args: &FuncArg) -> Result<(), EvalErr> {
+    match args.iter().find(|v| v.is_bool() || v.is_array() || v.is_symbolic()) {
+        Option::Some(v) => Result::Err(EvalErr::TypeMismatch(
+            format!("expected number, got {}", v),
+        )),
+        Option::None => Result::Ok(()),
+    }
+}
+
+fn dimensionless(args: &FuncArg) -> Result<(), EvalErr> {
+    numbers(args)?;
+    match args.iter().find(|v| v.is_quantity()) {
+        Option::Some(v) => Result::Err(EvalErr::DimensionMismatch(
+            format!("expected dimensionless number, got {}", v),
+        )),
+        Option::None => Result::Ok(()),
+    }
+}
+
+fn common_dim(args: &FuncArg) -> Result<Dimension, EvalErr> {
+    let dim = args[0].dim();
+    match args.iter().find(|v| v.dim() != dim) {
+        Option::Some(v) => Result::Err(EvalErr::DimensionMismatch(
+            format!("expected values with matching dimensions, got {} and {}", args[0], v),
+        )),
+        Option::None => Result::Ok(dim),
+    }
+}
+
+fn precise(ctx: &Context, v: &Value) -> Option<BigFloat> {
+    match v {
+        Value::BigFloat(b) => Option::Some(b.clone()),
+        Value::Complex(_) => Option::None,
+        v => v.to_bigfloat(ctx.precision?),
+    }
+}
+
+fn precise_all(ctx: &Context, args: &FuncArg) -> Option<Vec<BigFloat>> {
+    let prec = args
+        .iter()
+        .filter_map(|v| match v {
+            Value::BigFloat(b) => Option::Some(b.prec()),
+            _ => Option::None,
+        })
+        .max()
+        .or(ctx.precision)?;
+    args.iter().map(|v| v.to_bigfloat(prec)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factorial_size_estimate() {
+        let mut exact = Integer::one();
+        for n in 1..300u64 {
+            exact *= n;
+            assert_eq!(log2_factorial(n).floor() as u64 + 1, exact.bits());
+        }
+        // 254016! has 4194289 bits and 254017! has 4194307, either side of the cap
+        assert!(!factorial_overflows(&Integer::from(254016)));
+        assert!(factorial_overflows(&Integer::from(254017)));
+        assert!(!factorial_overflows(&Integer::from(250000)));
+    }
+
+    fn ints(values: &[i64]) -> Vec<Value> {
+        values.iter().map(|&i| Value::Int(Integer::from(i))).collect()
+    }
+
+    #[test]
+    fn selections() {
+        let ctx = Context::default();
+        assert_eq!(ncr(&ints(&[5, 2]), &ctx).unwrap().to_string(), "10");
+        assert_eq!(npr(&ints(&[5, 2]), &ctx).unwrap().to_string(), "20");
+        assert_eq!(ncr(&ints(&[5, 7]), &ctx).unwrap().to_string(), "0");
+        assert!(ncr(&ints(&[5, -1]), &ctx).is_err());
+        assert!(npr(&ints(&[5, -1]), &ctx).is_err());
+
+        let huge = [Value::Int(Integer::from(10).pow(30)), Value::Int(Integer::from(10).pow(29))];
+        assert!(matches!(ncr(&huge, &ctx), Result::Err(EvalErr::Overflow(_))));
+        assert!(matches!(npr(&huge, &ctx), Result::Err(EvalErr::Overflow(_))));
+        let wide = [Value::Int(Integer::from(10).pow(400)), Value::Int(Integer::from(2))];
+        assert_eq!(ncr(&wide, &ctx).unwrap().as_int().bits(), 2657);
+    }
+
+    #[test]
+    fn statistics() {
+        let ctx = Context::default();
+        let result = |f: FuncHandle, args: &[i64]| f(&ints(args), &ctx).unwrap().to_string();
+        let close = |f: FuncHandle, args: &[i64], expected: Float| {
+            (f(&ints(args), &ctx).unwrap().as_float() - expected).abs() < 1e-12
+        };
+        assert_eq!(result(median, &[3, 1, 2]), "2");
+        assert_eq!(result(median, &[4, 1, 3, 2]), "5/2");
+        assert!(median(&[], &ctx).is_err());
+
+        let data = [2, 4, 4, 4, 5, 5, 7, 9];
+        assert!(close(var, &data, 32.0 / 7.0));
+        assert!(close(pvar, &data, 4.0));
+        assert!(close(stddev, &data, (32.0f64 / 7.0).sqrt()));
+        assert!(close(pstddev, &data, 2.0));
+        assert!(var(&ints(&[1]), &ctx).is_err());
+
+        assert!(close(skew, &[1, 2, 3], 0.0));
+        assert!(close(skew, &[1, 2, 10], 3570.0 / 81.0 / (146.0f64 / 9.0).powf(1.5)));
+        assert!(close(kurtosis, &[1, 2, 3, 4], -1.36));
+        assert!(skew(&ints(&[1, 1, 1]), &ctx).is_err());
+    }
+
+    #[test]
+    fn percentile_level_comes_first() {
+        let ctx = Context::default();
+        let result = |f: FuncHandle, args: Vec<Value>| f(&args, &ctx).map(|v| v.to_string());
+        assert_eq!(result(percentile, ints(&[50, 1, 2, 3, 4])), Result::Ok("5/2".to_string()));
+        let quarter = Value::Float(0.25);
+        let items = Value::Vector(ints(&[1, 2, 3, 4, 5]));
+        assert_eq!(result(quantile, vec![quarter, items]), Result::Ok("2".to_string()));
+        assert!(result(percentile, ints(&[150, 1, 2])).is_err());
+        assert!(result(quantile, vec![Value::Float(-0.5), Value::Float(1.0)]).is_err());
+        assert!(result(percentile, ints(&[50])).is_err());
+    }
+
+    #[test]
+    fn bit_manipulation() {
+        let ctx = Context::default();
+        let byte = Context { int_width: Option::Some(8), ..Context::default() };
+        let result = |f: FuncHandle, args: &[i64], ctx: &Context| f(&ints(args), ctx).unwrap().to_string();
+        assert_eq!(result(rotl, &[0b1000_0001, 1, 8], &ctx), "3");
+        assert_eq!(result(rotr, &[0b1000_0001, 1, 8], &ctx), "192");
+        assert_eq!(result(rotl, &[1, -1, 8], &ctx), "128");
+        assert_eq!(result(rotl, &[1, 9, 8], &ctx), "2");
+        assert_eq!(result(rotr, &[1, 1], &byte), "-128");
+        assert!(rotl(&ints(&[1, 1]), &ctx).is_err());
+
+        assert_eq!(result(popcount, &[0b1011], &ctx), "3");
+        assert_eq!(result(popcount, &[-1, 16], &ctx), "16");
+        assert_eq!(result(popcount, &[-1], &byte), "8");
+        assert!(popcount(&ints(&[-1]), &ctx).is_err());
+
+        assert_eq!(result(clz, &[1, 32], &ctx), "31");
+        assert_eq!(result(clz, &[-1], &byte), "0");
+        assert_eq!(result(ctz, &[8], &ctx), "3");
+        assert_eq!(result(ctz, &[0, 16], &ctx), "16");
+        assert_eq!(result(ctz, &[0], &byte), "8");
+
+        assert_eq!(result(bit, &[5, 0], &ctx), "1");
+        assert_eq!(result(bit, &[5, 1], &ctx), "0");
+        assert_eq!(result(bit, &[-1, 100], &ctx), "1");
+        let message = |r: EvalResult| match r {
+            Result::Err(EvalErr::InvalidArgument(msg)) | Result::Err(EvalErr::IndexOutOfRange(msg)) => msg,
+            r => panic!("expected an error, got {:?}", r.map(|v| v.to_string())),
+        };
+        assert_eq!(message(bit(&ints(&[5, -1]), &ctx)), "expected non-negative bit position, got -1");
+        let far = [Value::Int(Integer::from(5)), Value::Int(Integer::from(10).pow(20))];
+        assert_eq!(message(bit(&far, &ctx)), "bit position 100000000000000000000 is out of range");
+    }
+
+    #[test]
+    fn shifts() {
+        let ctx = Context::default();
+        let int = |i: i64| Value::Int(Integer::from(i));
+        assert_eq!(int(1).shl(int(70), &ctx).unwrap().to_string(), "1180591620717411303424");
+        assert_eq!(int(-16).shr(int(2), &ctx).unwrap().to_string(), "-4");
+        assert_eq!(int(-1).shr(int(1000), &ctx).unwrap().to_string(), "-1");
+        assert!(int(1).shl(int(-1), &ctx).is_err());
+        assert!(matches!(int(1).shl(int(1 << 23), &ctx), Result::Err(EvalErr::Overflow(_))));
+
+        let byte = Context { int_width: Option::Some(8), ..Context::default() };
+        assert_eq!(int(1).shl(int(7), &byte).unwrap().to_string(), "-128");
+        assert_eq!(int(1).shl(int(300), &byte).unwrap().to_string(), "0");
+        assert_eq!(int(-128).shr(int(7), &byte).unwrap().to_string(), "-1");
+        assert!(Value::Float(1.5).shl(int(1), &ctx).is_err());
+    }
+
+    #[test]
+    fn precise_range() {
+        let ctx = Context { precision: Option::Some(30), ..Context::default() };
+        let power = |e: u32| Value::Int(Integer::from(10).pow(e));
+        let overflows = |r: EvalResult| matches!(r, Result::Err(EvalErr::Overflow(_)));
+        assert!(overflows(exp(&[power(20)], &ctx)));
+        assert!(overflows(exp(&[power(400)], &ctx)));
+        assert!(overflows(sin(&[power(100_000)], &ctx)));
+        assert!(overflows(cos(&[power(1001)], &ctx)));
+        assert!(overflows(tan(&[power(1001)], &ctx)));
+        let e = BigFloat::e(30);
+        assert!(overflows(Value::BigFloat(e.clone()).pow(Value::Float(1e20), &ctx)));
+        assert!(overflows(Value::BigFloat(e.clone()).pow(power(400), &ctx)));
+        let ten = BigFloat::from_integer(&Integer::from(10), 30);
+        assert!(overflows(Value::BigFloat(ten).pow(power(20), &ctx)));
+
+        assert_eq!(exp(&[power(1)], &ctx).unwrap().to_string(), "22026.4657948067165169579006453");
+        let half = Value::BigFloat(BigFloat::from_float(0.5, 30).unwrap());
+        let tiny = half.pow(power(14), &ctx).unwrap();
+        assert_eq!(tiny.to_string(), "7.59414047034492532046897767904e-30102999566399");
+        assert_eq!(sin(&[power(1000)], &ctx).unwrap().to_string(), "0.65335979821036985694809946804");
+    }
 }
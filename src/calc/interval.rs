@@ -0,0 +1,376 @@
+use crate::calc::common::Float;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Interval {
+    lo: Float,
+    hi: Float,
+}
+
+impl Interval {
+    pub fn new(lo: Float, hi: Float) -> Option<Interval> {
+        if lo.is_nan() || hi.is_nan() || lo > hi {
+            return Option::None;
+        }
+        Option::Some(Interval { lo, hi })
+    }
+
+    pub fn point(x: Float) -> Interval {
+        Interval { lo: x, hi: x }
+    }
+
+    // encloses a value that was rounded to the nearest float
+    pub fn around(x: Float) -> Interval {
+        Interval { lo: down(x), hi: up(x) }
+    }
+
+    pub fn lo(&self) -> Float {
+        self.lo
+    }
+
+    pub fn hi(&self) -> Float {
+        self.hi
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.lo.is_finite() && self.hi.is_finite()
+    }
+
+    pub fn contains_zero(&self) -> bool {
+        self.lo <= 0.0 && 0.0 <= self.hi
+    }
+
+    pub fn neg(&self) -> Interval {
+        Interval { lo: -self.hi, hi: -self.lo }
+    }
+
+    pub fn abs(&self) -> Interval {
+        if self.contains_zero() {
+            return Interval { lo: 0.0, hi: self.hi.max(-self.lo) };
+        }
+        if self.hi < 0.0 {
+            return self.neg();
+        }
+        *self
+    }
+
+    pub fn add(&self, rhs: &Interval) -> Interval {
+        Interval {
+            lo: add_down(self.lo, rhs.lo),
+            hi: add_up(self.hi, rhs.hi),
+        }
+    }
+
+    pub fn sub(&self, rhs: &Interval) -> Interval {
+        self.add(&rhs.neg())
+    }
+
+    pub fn mul(&self, rhs: &Interval) -> Interval {
+        let pairs = [
+            (self.lo, rhs.lo),
+            (self.lo, rhs.hi),
+            (self.hi, rhs.lo),
+            (self.hi, rhs.hi),
+        ];
+        Interval {
+            lo: pairs.iter().map(|&(a, b)| mul_down(a, b)).fold(Float::INFINITY, Float::min),
+            hi: pairs.iter().map(|&(a, b)| mul_up(a, b)).fold(Float::NEG_INFINITY, Float::max),
+        }
+    }
+
+    pub fn div(&self, rhs: &Interval) -> Option<Interval> {
+        if rhs.contains_zero() {
+            return Option::None;
+        }
+        let pairs = [
+            (self.lo, rhs.lo),
+            (self.lo, rhs.hi),
+            (self.hi, rhs.lo),
+            (self.hi, rhs.hi),
+        ];
+        Option::Some(Interval {
+            lo: pairs.iter().map(|&(a, b)| div_down(a, b)).fold(Float::INFINITY, Float::min),
+            hi: pairs.iter().map(|&(a, b)| div_up(a, b)).fold(Float::NEG_INFINITY, Float::max),
+        })
+    }
+
+    pub fn powi(&self, n: i32) -> Option<Interval> {
+        let power = self.powu(n.unsigned_abs());
+        if n < 0 {
+            return Interval::point(1.0).div(&power);
+        }
+        Option::Some(power)
+    }
+
+    fn powu(&self, n: u32) -> Interval {
+        if n % 2 == 1 {
+            return Interval { lo: signed_pow_down(self.lo, n), hi: signed_pow_up(self.hi, n) };
+        }
+        let (lo_abs, hi_abs) = (self.lo.abs(), self.hi.abs());
+        if self.contains_zero() {
+            return Interval { lo: 0.0, hi: pow_up(lo_abs.max(hi_abs), n) };
+        }
+        Interval {
+            lo: pow_down(lo_abs.min(hi_abs), n),
+            hi: pow_up(lo_abs.max(hi_abs), n),
+        }
+    }
+
+    pub fn powf(&self, rhs: &Interval) -> Option<Interval> {
+        // x ** y is monotone in each argument for x > 0, so the corners bound it
+        if self.lo <= 0.0 {
+            return Option::None;
+        }
+        let corners = [
+            self.lo.powf(rhs.lo),
+            self.lo.powf(rhs.hi),
+            self.hi.powf(rhs.lo),
+            self.hi.powf(rhs.hi),
+        ];
+        Interval::enclose(&corners)
+    }
+
+    pub fn sqrt(&self) -> Option<Interval> {
+        if self.lo < 0.0 {
+            return Option::None;
+        }
+        Option::Some(Interval { lo: sqrt_down(self.lo), hi: sqrt_up(self.hi) })
+    }
+
+    pub fn ln(&self) -> Option<Interval> {
+        if self.lo <= 0.0 {
+            return Option::None;
+        }
+        Option::Some(Interval { lo: down(self.lo.ln()), hi: up(self.hi.ln()) })
+    }
+
+    pub fn exp(&self) -> Interval {
+        Interval { lo: down(self.lo.exp()).max(0.0), hi: up(self.hi.exp()) }
+    }
+
+    pub fn sin(&self) -> Interval {
+        let mut out = Interval::around(self.lo.sin()).hull(&Interval::around(self.hi.sin()));
+        if self.has_angle(FRAC_PI_2) {
+            out.hi = 1.0;
+        }
+        if self.has_angle(-FRAC_PI_2) {
+            out.lo = -1.0;
+        }
+        out.clamp_unit()
+    }
+
+    pub fn cos(&self) -> Interval {
+        let mut out = Interval::around(self.lo.cos()).hull(&Interval::around(self.hi.cos()));
+        if self.has_angle(0.0) {
+            out.hi = 1.0;
+        }
+        if self.has_angle(PI) {
+            out.lo = -1.0;
+        }
+        out.clamp_unit()
+    }
+
+    pub fn tan(&self) -> Option<Interval> {
+        // tan is increasing between its poles at pi/2 + k*pi
+        if !self.is_finite() || self.hi - self.lo >= PI {
+            return Option::None;
+        }
+        let first = ((down(self.lo) - FRAC_PI_2) / PI).ceil();
+        let last = ((up(self.hi) - FRAC_PI_2) / PI).floor();
+        if first <= last {
+            return Option::None;
+        }
+        Option::Some(Interval { lo: down(self.lo.tan()), hi: up(self.hi.tan()) })
+    }
+
+    fn enclose(values: &[Float]) -> Option<Interval> {
+        if values.iter().any(|v| v.is_nan()) {
+            return Option::None;
+        }
+        let lo = values.iter().cloned().fold(Float::INFINITY, Float::min);
+        let hi = values.iter().cloned().fold(Float::NEG_INFINITY, Float::max);
+        Option::Some(Interval { lo: down(lo), hi: up(hi) })
+    }
+
+    fn hull(&self, other: &Interval) -> Interval {
+        Interval { lo: self.lo.min(other.lo), hi: self.hi.max(other.hi) }
+    }
+
+    fn clamp_unit(self) -> Interval {
+        Interval { lo: self.lo.max(-1.0), hi: self.hi.min(1.0) }
+    }
+
+    // whether the interval reaches `angle + 2k*pi` for some integer k
+    fn has_angle(&self, angle: Float) -> bool {
+        if !self.is_finite() || self.hi - self.lo >= TAU {
+            return true;
+        }
+        // widen slightly so a turning point on the edge is never missed
+        let first = ((down(self.lo) - angle) / TAU).ceil();
+        let last = ((up(self.hi) - angle) / TAU).floor();
+        first <= last
+    }
+}
+
+fn down(x: Float) -> Float {
+    x.next_down()
+}
+
+fn up(x: Float) -> Float {
+    x.next_up()
+}
+
+// the error terms below are exact, so results are only widened when the
+// rounded operation actually lost information
+
+fn add_err(a: Float, b: Float) -> (Float, Float) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+fn add_down(a: Float, b: Float) -> Float {
+    let (s, e) = add_err(a, b);
+    if e < 0.0 || e.is_nan() { down(s) } else { s }
+}
+
+fn add_up(a: Float, b: Float) -> Float {
+    let (s, e) = add_err(a, b);
+    if e > 0.0 || e.is_nan() { up(s) } else { s }
+}
+
+fn mul_down(a: Float, b: Float) -> Float {
+    let p = a * b;
+    let e = a.mul_add(b, -p);
+    if e < 0.0 || e.is_nan() { down(p) } else { p }
+}
+
+fn mul_up(a: Float, b: Float) -> Float {
+    let p = a * b;
+    let e = a.mul_add(b, -p);
+    if e > 0.0 || e.is_nan() { up(p) } else { p }
+}
+
+fn div_err(a: Float, b: Float) -> (Float, Float) {
+    // a - q*b has the sign of the quotient error times the sign of b
+    let q = a / b;
+    // a finite number over an infinite one is an exact zero
+    if b.is_infinite() && a.is_finite() {
+        return (q, 0.0);
+    }
+    let r = -q.mul_add(b, -a);
+    (q, r * b.signum())
+}
+
+fn div_down(a: Float, b: Float) -> Float {
+    let (q, e) = div_err(a, b);
+    if e < 0.0 || e.is_nan() { down(q) } else { q }
+}
+
+fn div_up(a: Float, b: Float) -> Float {
+    let (q, e) = div_err(a, b);
+    if e > 0.0 || e.is_nan() { up(q) } else { q }
+}
+
+fn sqrt_down(x: Float) -> Float {
+    let s = x.sqrt();
+    if s.mul_add(s, -x) > 0.0 { down(s).max(0.0) } else { s }
+}
+
+fn sqrt_up(x: Float) -> Float {
+    let s = x.sqrt();
+    if s.mul_add(s, -x) < 0.0 { up(s) } else { s }
+}
+
+// square-and-multiply stays directed because every factor is non-negative
+fn pow_with(x: Float, n: u32, mul: fn(Float, Float) -> Float) -> Float {
+    let (mut base, mut n, mut acc) = (x, n, 1.0);
+    while n > 0 {
+        if n & 1 == 1 {
+            acc = mul(acc, base);
+        }
+        base = mul(base, base);
+        n >>= 1;
+    }
+    acc
+}
+
+fn pow_down(x: Float, n: u32) -> Float {
+    pow_with(x, n, mul_down)
+}
+
+fn pow_up(x: Float, n: u32) -> Float {
+    pow_with(x, n, mul_up)
+}
+
+fn signed_pow_down(x: Float, n: u32) -> Float {
+    if x < 0.0 { -pow_up(-x, n) } else { pow_down(x, n) }
+}
+
+fn signed_pow_up(x: Float, n: u32) -> Float {
+    if x < 0.0 { -pow_down(-x, n) } else { pow_up(x, n) }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn iv(lo: Float, hi: Float) -> Interval {
+        Interval::new(lo, hi).unwrap()
+    }
+
+    #[test]
+    fn directed_rounding() {
+        // 0.1 + 0.2 and 0.1 * 3 both round up to 0.30000000000000004, so
+        // the lower bound is pulled down a step
+        let sum = Interval::point(0.1).add(&Interval::point(0.2));
+        assert_eq!((sum.lo(), sum.hi()), (0.3, 0.30000000000000004));
+        let product = Interval::point(0.1).mul(&Interval::point(3.0));
+        assert_eq!((product.lo(), product.hi()), (0.3, 0.30000000000000004));
+        let third = Interval::point(1.0).div(&Interval::point(3.0)).unwrap();
+        assert_eq!(third.hi(), third.lo().next_up());
+        assert!(third.lo() == 1.0 / 3.0 || third.hi() == 1.0 / 3.0);
+        // exact results stay points
+        assert!(Interval::point(0.5).add(&Interval::point(0.25)) == Interval::point(0.75));
+        assert!(Interval::point(3.0).mul(&Interval::point(4.0)) == Interval::point(12.0));
+        assert!(Interval::point(1.0).div(&iv(-1.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn integer_powers() {
+        assert!(iv(-2.0, 3.0).powi(2).unwrap() == iv(0.0, 9.0));
+        assert!(iv(-3.0, -2.0).powi(2).unwrap() == iv(4.0, 9.0));
+        assert!(iv(-2.0, 3.0).powi(3).unwrap() == iv(-8.0, 27.0));
+        assert!(iv(2.0, 4.0).powi(-1).unwrap() == iv(0.25, 0.5));
+        assert!(iv(-1.0, 1.0).powi(-2).is_none());
+        // the magnitude of i32::MIN does not fit in an i32
+        assert!(iv(1.0, 2.0).powi(i32::MIN).unwrap() == iv(0.0, 1.0));
+    }
+
+    #[test]
+    fn trigonometric_extremes() {
+        assert!(iv(1.0, 2.0).has_angle(FRAC_PI_2));
+        assert!(!iv(2.0, 3.0).has_angle(FRAC_PI_2));
+        assert!(iv(1.0, 2.0).sin().hi() == 1.0);
+        assert!(iv(3.0, 3.5).cos().lo() == -1.0);
+        assert!(iv(-0.5, 0.5).cos().hi() == 1.0);
+        // the extremes repeat every full turn
+        assert!(iv(TAU + 1.0, TAU + 2.0).sin().hi() == 1.0);
+        assert!(iv(0.0, 7.0).has_angle(-FRAC_PI_2));
+    }
+
+    #[test]
+    fn tangent_poles() {
+        assert!(iv(1.0, 2.0).tan().is_none());
+        assert!(iv(-1.0, 1.0).tan().is_some());
+        assert!(iv(4.0, 5.0).tan().is_none());
+        assert!(iv(2.0, 4.0).tan().is_some());
+    }
+}
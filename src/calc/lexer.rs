@@ -1,9 +1,29 @@
 use crate::Token;
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+
+// `m` is left out, so `3m` is three metres while `3n` is three nano; the
+// other letters name no unit on their own
+const SI_SUFFIXES: [(&str, i64); 9] = [
+    ("P", 15), ("T", 12), ("G", 9), ("M", 6), ("k", 3),
+    ("u", -6), ("n", -9), ("p", -12), ("f", -15),
+];
+
+const BINARY_SUFFIXES: [(&str, u32); 6] = [
+    ("Ki", 10), ("Mi", 20), ("Gi", 30), ("Ti", 40), ("Pi", 50), ("Ei", 60),
+];
+
+// suffixes that also spell a constant, which is looked up regardless of
+// case; they are only read with a trailing `B`, as in `2PiB`
+const AMBIGUOUS_SUFFIXES: [&str; 1] = ["Pi"];
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LexerErr {
     IllegalChar(char, usize),
     InvalidValue(usize),
+    InvalidSeparator(usize),
+    InvalidSuffix(String, usize),
+    AmbiguousSuffix(String, usize),
 }
 
 pub trait Tokeniser {
@@ -56,14 +76,13 @@ impl Lexer {
 
         while !found_eof_token {
             let token = self.next_token()?;
-            match token {
-                Token::Eof(_) => found_eof_token = true,
-                _ => {}
+            if let Token::Eof(_) = token {
+                found_eof_token = true;
             }
             tokens.push(token);
         }
 
-        return Ok(tokens);
+        Ok(tokens)
     }
 
     fn read_next_token(&mut self) -> Result<Token, LexerErr> {
@@ -75,53 +94,78 @@ impl Lexer {
 
         let start = self.cursor;
 
-        if ch.is_digit(10) {
+        if ch.is_ascii_digit() {
 
             let _ch = self.peek_char();
             let _radix = if ch == '0' { self.read_radix(_ch) } else { Option::None };
             if let Option::Some(r) = _radix {
                 self.advance();
                 ch = self.advance();
-                while ch.is_digit(r) {
+                while ch.is_digit(r) || ch == '_' {
                     ch = self.advance();
                 }
 
-                let value = &self.expr[start+2..self.cursor];
+                let value = self.read_digits(start+2, self.cursor, r)?;
                 if value.is_empty() {
                     return Err(LexerErr::InvalidValue(self.cursor))
                 }
-                return Ok(Token::Int(value.to_string(), r, start));
+                // `0x10k` could be read either way, so neither is assumed
+                if let Option::Some((suffix, _)) = self.read_magnitude() {
+                    return Err(LexerErr::InvalidSuffix(suffix, self.cursor));
+                }
+                return Ok(Token::Int(value, r, start));
             }
 
             ch = self.advance();
-            while ch.is_digit(10) {
+            while ch.is_ascii_digit() || ch == '_' {
                 ch = self.advance();
             }
 
-            if ch != '.' && ch != 'e' && ch != 'E' {
-                let value = &self.expr[start..self.cursor];
-                return Ok(Token::Int(value.to_string(), 10, start));
-            }
-
+            let mut is_float = false;
+            let mut has_exponent = false;
             if ch == '.' {
+                is_float = true;
                 ch = self.advance();
-                while ch.is_digit(10) {
+                while ch.is_ascii_digit() || ch == '_' {
                     ch = self.advance();
                 }
             }
 
             if ch == 'e' || ch == 'E' {
+                is_float = true;
+                has_exponent = true;
                 ch = self.advance();
                 if ch == '+' || ch == '-' {
                     ch = self.advance();
                 }
-                while ch.is_digit(10) {
+                while ch.is_ascii_digit() || ch == '_' {
                     ch = self.advance();
                 }
             }
 
-            let value = &self.expr[start..self.cursor];
-            return Ok(Token::Float(value.to_string(), start));
+            let value = self.read_digits(start, self.cursor, 10)?;
+            let Option::Some((suffix, factor)) = self.read_magnitude() else {
+                if is_float {
+                    return Ok(Token::Float(value, start));
+                }
+                return Ok(Token::Int(value, 10, start));
+            };
+            if has_exponent {
+                return Err(LexerErr::InvalidSuffix(suffix, self.cursor));
+            }
+            if AMBIGUOUS_SUFFIXES.contains(&suffix.as_str()) {
+                return Err(LexerErr::AmbiguousSuffix(suffix, self.cursor));
+            }
+
+            let scaled = match BigDecimal::from_str(&value) {
+                Result::Ok(v) => v * factor,
+                Result::Err(_) => return Err(LexerErr::InvalidValue(start)),
+            };
+            self.cursor += suffix.chars().count();
+            if !is_float && scaled.is_integer() {
+                return Ok(Token::Int(scaled.with_scale(0).to_plain_string(), 10, start));
+            }
+            return Ok(Token::Float(scaled.normalized().to_plain_string(), start));
         }
 
         if ch.is_ascii_alphabetic() {
@@ -129,15 +173,27 @@ impl Lexer {
             while ch.is_ascii_alphabetic() {
                 ch = self.advance();
             }
-            let value = &self.expr[start..self.cursor];
+            let value = self.slice(start, self.cursor);
+            if value == "to" || value == "in" {
+                return Ok(Token::To(start));
+            }
             return Ok(Token::Name(value.to_string(), start));
         }
 
         if ch == '+' {
+            if self.peek_char() == '/' && self.char_at(self.cursor+2) == '-' {
+                self.cursor += 3;
+                return Ok(Token::PlusMinus(start));
+            }
             self.advance();
             return Ok(Token::Plus(start));
         }
 
+        if ch == '±' {
+            self.advance();
+            return Ok(Token::PlusMinus(start));
+        }
+
         if ch == '-' {
             self.advance();
             return Ok(Token::Minus(start));
@@ -164,13 +220,23 @@ impl Lexer {
         }
 
         if ch == '&' {
-            self.advance();
-            return Ok(Token::And(start));
+            return match self.advance() {
+                '&' => {
+                    self.advance();
+                    Ok(Token::AndAnd(start))
+                },
+                _ => Ok(Token::And(start)),
+            };
         }
 
         if ch == '|' {
-            self.advance();
-            return Ok(Token::Or(start));
+            return match self.advance() {
+                '|' => {
+                    self.advance();
+                    Ok(Token::OrOr(start))
+                },
+                _ => Ok(Token::Or(start)),
+            };
         }
 
         if ch == '~' {
@@ -183,6 +249,54 @@ impl Lexer {
             return Ok(Token::Xor(start));
         }
 
+        if ch == '=' {
+            return match self.advance() {
+                '=' => {
+                    self.advance();
+                    Ok(Token::Eq(start))
+                },
+                _ => Ok(Token::Assign(start)),
+            };
+        }
+
+        if ch == '!' {
+            return match self.advance() {
+                '=' => {
+                    self.advance();
+                    Ok(Token::Ne(start))
+                },
+                _ => Err(LexerErr::IllegalChar(ch, start)),
+            };
+        }
+
+        if ch == '<' {
+            return match self.advance() {
+                '=' => {
+                    self.advance();
+                    Ok(Token::Le(start))
+                },
+                '<' => {
+                    self.advance();
+                    Ok(Token::Shl(start))
+                },
+                _ => Ok(Token::Lt(start)),
+            };
+        }
+
+        if ch == '>' {
+            return match self.advance() {
+                '=' => {
+                    self.advance();
+                    Ok(Token::Ge(start))
+                },
+                '>' => {
+                    self.advance();
+                    Ok(Token::Shr(start))
+                },
+                _ => Ok(Token::Gt(start)),
+            };
+        }
+
         if ch == ',' {
             self.advance();
             return Ok(Token::Comma(start));
@@ -198,15 +312,71 @@ impl Lexer {
             return Ok(Token::Rparen(start));
         }
 
+        if ch == '[' {
+            self.advance();
+            return Ok(Token::Lbracket(start));
+        }
+
+        if ch == ']' {
+            self.advance();
+            return Ok(Token::Rbracket(start));
+        }
+
         if ch == '\0' {
             return Ok(Token::Eof(start));
         }
 
-        return Err(LexerErr::IllegalChar(ch, start));
+        Err(LexerErr::IllegalChar(ch, start))
     }
 
     fn curr_char(&mut self) -> char {
-        self.expr.chars().nth(self.cursor).unwrap_or('\0')
+        self.char_at(self.cursor)
+    }
+
+    fn char_at(&self, index: usize) -> char {
+        self.expr.chars().nth(index).unwrap_or('\0')
+    }
+
+    // the cursor counts characters, so slicing by byte offsets would split
+    // multi-byte characters such as '±'
+    fn slice(&self, start: usize, end: usize) -> &str {
+        let mut indices = self.expr.char_indices().map(|(i, _)| i).chain([self.expr.len()]);
+        let from = indices.clone().nth(start).unwrap_or(self.expr.len());
+        let to = indices.nth(end).unwrap_or(self.expr.len());
+        &self.expr[from..to]
+    }
+
+    // the digits between `start` and `end` with their `_` separators removed;
+    // a separator has to sit between two digits
+    fn read_digits(&self, start: usize, end: usize, radix: u32) -> Result<String, LexerErr> {
+        let chars: Vec<char> = self.slice(start, end).chars().collect();
+        for (i, &c) in chars.iter().enumerate() {
+            let digit_before = i > 0 && chars[i-1].is_digit(radix);
+            let digit_after = chars.get(i+1).is_some_and(|c| c.is_digit(radix));
+            if c == '_' && !(digit_before && digit_after) {
+                return Err(LexerErr::InvalidSeparator(start + i));
+            }
+        }
+        Ok(chars.into_iter().filter(|&c| c != '_').collect())
+    }
+
+    // a magnitude suffix written straight after a number, as in `4.7k` or
+    // `16KiB`, with the factor it stands for; any other run of letters is
+    // left alone to be read as a unit
+    fn read_magnitude(&self) -> Option<(String, BigDecimal)> {
+        let mut end = self.cursor;
+        while self.char_at(end).is_ascii_alphabetic() {
+            end += 1;
+        }
+        let word = self.slice(self.cursor, end);
+        let prefix = word.strip_suffix('B').unwrap_or(word);
+        if let Option::Some((_, exp)) = SI_SUFFIXES.iter().find(|(s, _)| *s == prefix) {
+            return Option::Some((word.to_string(), BigDecimal::new(1.into(), -exp)));
+        }
+        if let Option::Some((_, bits)) = BINARY_SUFFIXES.iter().find(|(s, _)| *s == prefix) {
+            return Option::Some((word.to_string(), BigDecimal::from(1u64 << bits)));
+        }
+        Option::None
     }
 
     fn read_radix(&self, ch: char) -> Option<u32> {
@@ -219,7 +389,7 @@ impl Lexer {
     }
 
     fn peek_char(&mut self) -> char {
-        self.expr.chars().nth(self.cursor+1).unwrap_or('\0')
+        self.char_at(self.cursor+1)
     }
 
     fn advance(&mut self) -> char {
@@ -266,7 +436,7 @@ mod tests {
         );
         assert_eq!(
             tokenise("**".to_string()),
-            Ok(vec![Token::Pow(0), Token::Eof(1)])
+            Ok(vec![Token::Pow(0), Token::Eof(2)])
         );
         assert_eq!(
             tokenise("&".to_string()),
@@ -284,6 +454,74 @@ mod tests {
             tokenise("^".to_string()),
             Ok(vec![Token::Xor(0), Token::Eof(1)])
         );
+        assert_eq!(
+            tokenise("±".to_string()),
+            Ok(vec![Token::PlusMinus(0), Token::Eof(1)])
+        );
+        assert_eq!(
+            tokenise("+/-".to_string()),
+            Ok(vec![Token::PlusMinus(0), Token::Eof(3)])
+        );
+    }
+
+    #[test]
+    fn tokenise_after_multibyte_char() {
+        assert_eq!(
+            tokenise("1±0.5".to_string()),
+            Ok(vec![
+                Token::Int("1".to_string(), 10, 0),
+                Token::PlusMinus(1),
+                Token::Float("0.5".to_string(), 2),
+                Token::Eof(5),
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenise_comparison_operators() {
+        assert_eq!(
+            tokenise("==".to_string()),
+            Ok(vec![Token::Eq(0), Token::Eof(2)])
+        );
+        assert_eq!(
+            tokenise("!=".to_string()),
+            Ok(vec![Token::Ne(0), Token::Eof(2)])
+        );
+        assert_eq!(
+            tokenise("< <=".to_string()),
+            Ok(vec![Token::Lt(0), Token::Le(2), Token::Eof(4)])
+        );
+        assert_eq!(
+            tokenise("> >=".to_string()),
+            Ok(vec![Token::Gt(0), Token::Ge(2), Token::Eof(4)])
+        );
+        assert_eq!(
+            tokenise("&& ||".to_string()),
+            Ok(vec![Token::AndAnd(0), Token::OrOr(3), Token::Eof(5)])
+        );
+        assert_eq!(
+            tokenise("<< >>".to_string()),
+            Ok(vec![Token::Shl(0), Token::Shr(3), Token::Eof(5)])
+        );
+        assert_eq!(tokenise("=".to_string()), Ok(vec![Token::Assign(0), Token::Eof(1)]));
+        assert_eq!(tokenise("!".to_string()), Err(LexerErr::IllegalChar('!', 0)));
+    }
+
+    #[test]
+    fn tokenise_conversion_keywords() {
+        assert_eq!(
+            tokenise("x to km".to_string()),
+            Ok(vec![
+                Token::Name("x".to_string(), 0),
+                Token::To(2),
+                Token::Name("km".to_string(), 5),
+                Token::Eof(7),
+            ])
+        );
+        assert_eq!(
+            tokenise("in".to_string()),
+            Ok(vec![Token::To(0), Token::Eof(2)])
+        );
     }
 
     #[test]
@@ -300,6 +538,10 @@ mod tests {
             tokenise(",".to_string()),
             Ok(vec![Token::Comma(0), Token::Eof(1)])
         );
+        assert_eq!(
+            tokenise("[]".to_string()),
+            Ok(vec![Token::Lbracket(0), Token::Rbracket(1), Token::Eof(2)])
+        );
     }
 
     #[test]
@@ -353,4 +595,64 @@ mod tests {
             Ok(vec![Token::Float("5e-10".to_string(), 0), Token::Eof(5)])
         );
     }
+
+    #[test]
+    fn tokenise_digit_separators() {
+        assert_eq!(
+            tokenise("1_000_000".to_string()),
+            Ok(vec![Token::Int("1000000".to_string(), 10, 0), Token::Eof(9)])
+        );
+        assert_eq!(
+            tokenise("0xFFFF_0000".to_string()),
+            Ok(vec![Token::Int("FFFF0000".to_string(), 16, 0), Token::Eof(11)])
+        );
+        assert_eq!(
+            tokenise("1_000.000_1".to_string()),
+            Ok(vec![Token::Float("1000.0001".to_string(), 0), Token::Eof(11)])
+        );
+        assert_eq!(tokenise("1__0".to_string()), Err(LexerErr::InvalidSeparator(1)));
+        assert_eq!(tokenise("10_".to_string()), Err(LexerErr::InvalidSeparator(2)));
+        assert_eq!(tokenise("0x_F".to_string()), Err(LexerErr::InvalidSeparator(2)));
+        assert_eq!(tokenise("1_.5".to_string()), Err(LexerErr::InvalidSeparator(1)));
+    }
+
+    #[test]
+    fn tokenise_magnitude_suffixes() {
+        assert_eq!(
+            tokenise("4.7k".to_string()),
+            Ok(vec![Token::Float("4700".to_string(), 0), Token::Eof(4)])
+        );
+        assert_eq!(
+            tokenise("10M".to_string()),
+            Ok(vec![Token::Int("10000000".to_string(), 10, 0), Token::Eof(3)])
+        );
+        assert_eq!(
+            tokenise("3.3u".to_string()),
+            Ok(vec![Token::Float("0.0000033".to_string(), 0), Token::Eof(4)])
+        );
+        assert_eq!(
+            tokenise("2Gi".to_string()),
+            Ok(vec![Token::Int("2147483648".to_string(), 10, 0), Token::Eof(3)])
+        );
+        assert_eq!(
+            tokenise("16KiB".to_string()),
+            Ok(vec![Token::Int("16384".to_string(), 10, 0), Token::Eof(5)])
+        );
+        assert_eq!(
+            tokenise("3m".to_string()),
+            Ok(vec![
+                Token::Int("3".to_string(), 10, 0),
+                Token::Name("m".to_string(), 1),
+                Token::Eof(2),
+            ])
+        );
+        assert_eq!(tokenise("1e3k".to_string()), Err(LexerErr::InvalidSuffix("k".to_string(), 3)));
+        assert_eq!(tokenise("0x10Ki".to_string()), Err(LexerErr::InvalidSuffix("Ki".to_string(), 4)));
+        // `2Pi` could be two pebi or two pi
+        assert_eq!(tokenise("2Pi".to_string()), Err(LexerErr::AmbiguousSuffix("Pi".to_string(), 1)));
+        assert_eq!(
+            tokenise("2PiB".to_string()),
+            Ok(vec![Token::Int((1u64 << 51).to_string(), 10, 0), Token::Eof(4)])
+        );
+    }
 }
@@ -0,0 +1,324 @@
+use crate::calc::common::{Float, Integer};
+use crate::calc::context::Context;
+use crate::calc::eval::EvalErr;
+use crate::calc::value::Value;
+use num_traits::{One, Signed, ToPrimitive};
+use std::cmp::Ordering;
+use std::fmt;
+
+// largest number of entries `identity` will allocate
+pub const MAX_MATRIX_ITEMS: usize = 1 << 20;
+
+// a dense matrix of values, stored row by row
+#[derive(Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    items: Vec<Value>,
+}
+
+impl Matrix {
+    // None when there are no rows or they differ in length
+    pub fn from_rows(rows: Vec<Vec<Value>>) -> Option<Matrix> {
+        let cols = rows.first()?.len();
+        if cols == 0 || rows.iter().any(|r| r.len() != cols) {
+            return Option::None;
+        }
+        Option::Some(Matrix { rows: rows.len(), cols, items: rows.into_iter().flatten().collect() })
+    }
+
+    pub fn column(items: Vec<Value>) -> Option<Matrix> {
+        Matrix::from_rows(items.into_iter().map(|v| vec![v]).collect())
+    }
+
+    pub fn identity(n: usize) -> Matrix {
+        let items = (0..n * n)
+            .map(|k| if k / n == k % n { Value::Int(Integer::one()) } else { Value::zero() })
+            .collect();
+        Matrix { rows: n, cols: n, items }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn items(&self) -> &[Value] {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<Value> {
+        self.items
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> &Value {
+        &self.items[i * self.cols + j]
+    }
+
+    pub fn row(&self, i: usize) -> Vec<Value> {
+        self.items[i * self.cols..(i + 1) * self.cols].to_vec()
+    }
+
+    pub fn shape(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let items = (0..self.rows * self.cols)
+            .map(|k| self.get(k % self.rows, k / self.rows).clone())
+            .collect();
+        Matrix { rows: self.cols, cols: self.rows, items }
+    }
+
+    pub fn map<F>(self, f: F) -> Matrix
+    where
+        F: Fn(Value) -> Value,
+    {
+        let items = self.items.into_iter().map(f).collect();
+        Matrix { items, ..self }
+    }
+
+    pub fn try_map<F>(self, f: F) -> Result<Matrix, EvalErr>
+    where
+        F: Fn(Value) -> Result<Value, EvalErr>,
+    {
+        let items = self.items.into_iter().map(f).collect::<Result<Vec<_>, _>>()?;
+        Result::Ok(Matrix { items, ..self })
+    }
+
+    pub fn zip_with<F>(self, rhs: Matrix, op: &str, f: F) -> Result<Matrix, EvalErr>
+    where
+        F: Fn(Value, Value) -> Result<Value, EvalErr>,
+    {
+        if self.rows != rhs.rows || self.cols != rhs.cols {
+            return Result::Err(EvalErr::ShapeMismatch(format!(
+                "{} {} {}", self.shape(), op, rhs.shape()
+            )));
+        }
+        let items = self
+            .items
+            .into_iter()
+            .zip(rhs.items)
+            .map(|(x, y)| f(x, y))
+            .collect::<Result<Vec<_>, _>>()?;
+        Result::Ok(Matrix { items, ..self })
+    }
+
+    pub fn mul(&self, rhs: &Matrix, ctx: &Context) -> Result<Matrix, EvalErr> {
+        if self.cols != rhs.rows {
+            return Result::Err(EvalErr::ShapeMismatch(format!(
+                "cannot multiply {} by {}", self.shape(), rhs.shape()
+            )));
+        }
+        let mut items = Vec::with_capacity(self.rows * rhs.cols);
+        for i in 0..self.rows {
+            for j in 0..rhs.cols {
+                let mut total = self.get(i, 0).clone().mul(rhs.get(0, j).clone(), ctx)?;
+                for k in 1..self.cols {
+                    total = total.add(self.get(i, k).clone().mul(rhs.get(k, j).clone(), ctx)?, ctx)?;
+                }
+                items.push(total);
+            }
+        }
+        Result::Ok(Matrix { rows: self.rows, cols: rhs.cols, items })
+    }
+
+    pub fn powi(&self, exp: &Integer, ctx: &Context) -> Result<Matrix, EvalErr> {
+        self.square("**")?;
+        let mut base = if exp.is_negative() { self.inverse(ctx)? } else { self.clone() };
+        let Option::Some(mut n) = exp.abs().to_u64() else {
+            return Result::Err(EvalErr::Overflow(format!("matrix power {} is too large", exp)));
+        };
+        let mut result = Matrix::identity(self.rows);
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.mul(&base, ctx)?;
+            }
+            n >>= 1;
+            if n > 0 {
+                base = base.mul(&base, ctx)?;
+            }
+        }
+        Result::Ok(result)
+    }
+
+    pub fn trace(&self, ctx: &Context) -> Result<Value, EvalErr> {
+        self.square("trace")?;
+        let mut total = self.get(0, 0).clone();
+        for i in 1..self.rows {
+            total = total.add(self.get(i, i).clone(), ctx)?;
+        }
+        Result::Ok(total)
+    }
+
+    pub fn det(&self, ctx: &Context) -> Result<Value, EvalErr> {
+        self.square("det")?;
+        let (_, det) = self.clone().reduce(self.cols, ctx)?;
+        Result::Ok(det)
+    }
+
+    pub fn rank(&self, ctx: &Context) -> Result<usize, EvalErr> {
+        let (rank, _) = self.clone().reduce(self.cols, ctx)?;
+        Result::Ok(rank)
+    }
+
+    pub fn inverse(&self, ctx: &Context) -> Result<Matrix, EvalErr> {
+        self.square("inv")?;
+        self.solve(&Matrix::identity(self.rows), ctx)
+    }
+
+    // the matrix `x` with `self * x == rhs`
+    pub fn solve(&self, rhs: &Matrix, ctx: &Context) -> Result<Matrix, EvalErr> {
+        self.square("solve")?;
+        if rhs.rows != self.rows {
+            return Result::Err(EvalErr::ShapeMismatch(format!(
+                "cannot solve a {} system for a right-hand side of {}", self.shape(), rhs.shape()
+            )));
+        }
+        let mut system = self.augment(rhs);
+        let (rank, _) = system.reduce(self.cols, ctx)?;
+        if rank < self.rows {
+            return Result::Err(EvalErr::SingularMatrix(format!("{}", self)));
+        }
+        let items = (0..self.rows)
+            .flat_map(|i| system.row(i).split_off(self.cols))
+            .collect();
+        Result::Ok(Matrix { rows: self.rows, cols: rhs.cols, items })
+    }
+
+    fn square(&self, name: &str) -> Result<(), EvalErr> {
+        if self.rows != self.cols {
+            return Result::Err(EvalErr::ShapeMismatch(format!(
+                "{} needs a square matrix, got {}", name, self.shape()
+            )));
+        }
+        Result::Ok(())
+    }
+
+    fn augment(&self, rhs: &Matrix) -> Matrix {
+        let items = (0..self.rows)
+            .flat_map(|i| [self.row(i), rhs.row(i)].concat())
+            .collect();
+        Matrix { rows: self.rows, cols: self.cols + rhs.cols, items }
+    }
+
+    // Gauss-Jordan elimination over the first `width` columns, returning the
+    // rank and the determinant of that block when it is square
+    fn reduce(&mut self, width: usize, ctx: &Context) -> Result<(usize, Value), EvalErr> {
+        // each column is measured against its own scale, and the right-hand
+        // side says nothing about which pivots vanish
+        let tolerance: Vec<Float> = (0..width)
+            .map(|j| {
+                (0..self.rows).map(|i| size(self.get(i, j))).fold(0.0, Float::max)
+                    * Float::EPSILON
+                    * self.rows.max(width) as Float
+            })
+            .collect();
+        let negligible = |v: &Value, col: usize| v.is_zero() || (v.as_ratio().is_none() && size(v) <= tolerance[col]);
+
+        let mut det = Value::Int(Integer::one());
+        let mut rank = 0;
+        for col in 0..width {
+            if rank == self.rows {
+                break;
+            }
+            // the largest pivot keeps floating point error small
+            let pivot = (rank..self.rows)
+                .max_by(|&a, &b| {
+                    size(self.get(a, col)).partial_cmp(&size(self.get(b, col))).unwrap_or(Ordering::Equal)
+                })
+                .filter(|&r| !negligible(self.get(r, col), col));
+            let Option::Some(pivot) = pivot else {
+                det = Value::zero();
+                continue;
+            };
+            if pivot != rank {
+                for j in 0..self.cols {
+                    self.items.swap(pivot * self.cols + j, rank * self.cols + j);
+                }
+                det = det.neg(ctx)?;
+            }
+
+            let scale = self.get(rank, col).clone();
+            det = det.mul(scale.clone(), ctx)?;
+            for j in 0..self.cols {
+                let k = rank * self.cols + j;
+                self.items[k] = self.items[k].clone().div(scale.clone(), ctx)?;
+            }
+            for i in (0..self.rows).filter(|&i| i != rank) {
+                let factor = self.get(i, col).clone();
+                if factor.is_zero() {
+                    continue;
+                }
+                for j in 0..self.cols {
+                    let step = factor.clone().mul(self.get(rank, j).clone(), ctx)?;
+                    let k = i * self.cols + j;
+                    self.items[k] = self.items[k].clone().sub(step, ctx)?;
+                }
+            }
+            rank += 1;
+        }
+        if rank < width.min(self.rows) {
+            det = Value::zero();
+        }
+        Result::Ok((rank, det))
+    }
+}
+
+fn size(v: &Value) -> Float {
+    v.as_complex().norm()
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = (0..self.rows)
+            .map(|i| {
+                let row: Vec<String> = self.row(i).iter().map(|v| v.to_string()).collect();
+                format!("[{}]", row.join(", "))
+            })
+            .collect();
+        write!(f, "[{}]", rows.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[i64]]) -> Matrix {
+        let rows = rows
+            .iter()
+            .map(|r| r.iter().map(|&x| Value::Int(Integer::from(x))).collect())
+            .collect();
+        Matrix::from_rows(rows).unwrap()
+    }
+
+    #[test]
+    fn exact_elimination() {
+        let ctx = Context::default();
+        let m = matrix(&[&[1, 2], &[3, 4]]);
+        assert_eq!(m.det(&ctx).unwrap().to_string(), "-2");
+        assert_eq!(m.inverse(&ctx).unwrap().to_string(), "[[-2, 1], [3/2, -1/2]]");
+        assert_eq!(matrix(&[&[1, 2, 3], &[2, 4, 6]]).rank(&ctx).unwrap(), 1);
+        assert!(matrix(&[&[1, 2], &[2, 4]]).inverse(&ctx).is_err());
+    }
+
+    #[test]
+    fn small_pivots() {
+        let ctx = Context::default();
+        let diagonal = |a: Float, b: Float| {
+            let rows = vec![vec![Value::Float(a), Value::zero()], vec![Value::zero(), Value::Float(b)]];
+            Matrix::from_rows(rows).unwrap()
+        };
+        let inverse = diagonal(1e-20, 1.0).inverse(&ctx).unwrap();
+        assert_eq!(inverse.get(0, 0).as_float(), 1e20);
+        let inverse = diagonal(1e-20, 1e-20).inverse(&ctx).unwrap();
+        assert_eq!(inverse.get(1, 1).as_float(), 1e20);
+        assert_eq!(diagonal(1e-20, 1.0).rank(&ctx).unwrap(), 2);
+        assert_eq!(diagonal(1e-20, 1.0).det(&ctx).unwrap().as_float(), 1e-20);
+
+        let rows = vec![vec![Value::Float(0.1), Value::Float(0.2)], vec![Value::Float(0.3), Value::Float(0.6)]];
+        let singular = Matrix::from_rows(rows).unwrap();
+        assert_eq!(singular.rank(&ctx).unwrap(), 1);
+        assert!(singular.inverse(&ctx).is_err());
+    }
+}
@@ -1,10 +1,22 @@
+pub mod bigfloat;
 pub mod common;
 pub mod context;
+pub mod format;
+pub mod fraction;
 pub mod eval;
 pub mod functions;
+pub mod interval;
 pub mod constants;
 pub mod lexer;
+pub mod matrix;
 pub mod nodes;
 pub mod parser;
+pub mod polynomial;
+pub mod quadrature;
+pub mod rootfinding;
+pub mod statistics;
+pub mod symbolic;
 pub mod token;
+pub mod uncertain;
+pub mod units;
 pub mod value;
@@ -16,15 +16,29 @@ pub enum BinOp {
     Div,
     Mod,
     Pow,
+    PlusMinus,
     And,
     Or,
     Xor,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    AndAnd,
+    OrOr,
+    Shl,
+    Shr,
+    To,
+    Equation,
 }
 
 #[derive(Clone, PartialEq)]
 pub enum Expr {
     UnOp(UnOp, Box<Expr>),
     BinOp(BinOp, Box<Expr>, Box<Expr>),
+    Index(Box<Expr>, Box<Expr>),
     Atom(Atom),
 }
 
@@ -33,7 +47,32 @@ pub enum Atom {
     Int(Integer),
     Float(Float),
     Const(String),
+    Unit(String),
+    Quantity(Box<Atom>, String),
     Func(String, Vec<Expr>),
+    Vector(Vec<Expr>),
+}
+
+impl BinOp {
+    // left and right binding power; the right one is lower for the
+    // right-associative `**`
+    pub fn binding_power(&self) -> (f32, f32) {
+        match self {
+            BinOp::Equation => (0.3, 0.4),
+            BinOp::To => (0.5, 0.6),
+            BinOp::OrOr => (0.61, 0.62),
+            BinOp::AndAnd => (0.65, 0.66),
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => (0.7, 0.8),
+            BinOp::Xor => (1.0, 1.1),
+            BinOp::Or => (2.0, 2.1),
+            BinOp::And => (3.0, 3.1),
+            BinOp::Shl | BinOp::Shr => (3.5, 3.6),
+            BinOp::Plus | BinOp::Minus => (4.0, 4.1),
+            BinOp::Mul | BinOp::Div | BinOp::Mod => (5.0, 5.1),
+            BinOp::PlusMinus => (5.5, 5.6),
+            BinOp::Pow => (6.1, 6.0),
+        }
+    }
 }
 
 impl fmt::Debug for UnOp {
@@ -55,9 +94,22 @@ impl fmt::Debug for BinOp {
             BinOp::Div => "/",
             BinOp::Mod => "%",
             BinOp::Pow => "**",
+            BinOp::PlusMinus => "±",
             BinOp::And => "&",
             BinOp::Or => "|",
             BinOp::Xor => "^",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::AndAnd => "&&",
+            BinOp::OrOr => "||",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::To => "to",
+            BinOp::Equation => "=",
         };
         write!(f, "{}", sym)
     }
@@ -72,6 +124,7 @@ impl fmt::Debug for Expr {
             Expr::BinOp(op, left, right) => {
                 write!(f, "({:?} {:?} {:?})", left, op, right)
             }
+            Expr::Index(target, index) => write!(f, "{:?}[{:?}]", target, index),
             Expr::Atom(a) => write!(f, "{:?}", a),
         }
     }
@@ -83,6 +136,8 @@ impl fmt::Debug for Atom {
             Atom::Int(i) => write!(f, "{}", i),
             Atom::Float(fl) => write!(f, "{}", fl),
             Atom::Const(name) => write!(f, "{}", name),
+            Atom::Unit(name) => write!(f, "{}", name),
+            Atom::Quantity(value, unit) => write!(f, "{:?} {}", value, unit),
             Atom::Func(name, args) => {
                 write!(f, "{}(", name)?;
                 for (i, arg) in args.iter().enumerate() {
@@ -93,6 +148,73 @@ impl fmt::Debug for Atom {
                 }
                 write!(f, ")")
             }
+            Atom::Vector(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+// writes an expression back in the syntax the parser reads, with only the
+// parentheses that precedence needs
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::UnOp(op, expr) => match **expr {
+                Expr::BinOp(..) => write!(f, "{:?}({})", op, expr),
+                _ => write!(f, "{:?}{}", op, expr),
+            },
+            Expr::BinOp(op, lhs, rhs) => {
+                let (lbp, rbp) = op.binding_power();
+                let wrap = |e: &Expr, left: bool| match e {
+                    Expr::BinOp(inner, ..) => {
+                        let (inner_lbp, _) = inner.binding_power();
+                        inner_lbp < lbp || (inner_lbp == lbp && left == (rbp < lbp))
+                    },
+                    // unary operators bind tighter than `**`, so `-x**2` is
+                    // `(-x)**2`; the parentheses just make that plain
+                    Expr::UnOp(..) => left && *op == BinOp::Pow,
+                    _ => false,
+                };
+                let lhs = if wrap(lhs, true) { format!("({})", lhs) } else { lhs.to_string() };
+                let rhs = if wrap(rhs, false) { format!("({})", rhs) } else { rhs.to_string() };
+                match op {
+                    BinOp::Pow => write!(f, "{}{:?}{}", lhs, op, rhs),
+                    _ => write!(f, "{} {:?} {}", lhs, op, rhs),
+                }
+            }
+            Expr::Index(target, index) => match **target {
+                Expr::Atom(_) => write!(f, "{}[{}]", target, index),
+                _ => write!(f, "({})[{}]", target, index),
+            },
+            Expr::Atom(a) => write!(f, "{}", a),
+        }
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // f64's Debug switches to an exponent for very large and small
+            // literals, which the lexer reads back all the same
+            Atom::Float(x) => write!(f, "{:?}", x),
+            Atom::Quantity(value, unit) => write!(f, "{} {}", value, unit),
+            Atom::Func(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            Atom::Vector(items) => {
+                let items: Vec<String> = items.iter().map(|a| a.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            a => write!(f, "{:?}", a),
         }
     }
 }
@@ -44,28 +44,36 @@ where
 
     fn parse_expr(&mut self, min_bp: f32) -> Result<Expr, ParserErr> {
         let mut lhs = match self.consume()? {
-            Token::Int(v, radix, _) => Expr::Atom(self.parse_int(v, radix)?),
-            Token::Float(v, _) => Expr::Atom(self.parse_float(v)?),
+            Token::Int(v, radix, _) => {
+                let atom = self.parse_int(v, radix)?;
+                self.parse_number(atom)?
+            }
+            Token::Float(v, _) => {
+                let atom = self.parse_float(v)?;
+                self.parse_number(atom)?
+            }
             Token::Name(v, _) => match self.peek()? {
                 Token::Lparen(_) => Expr::Atom(Atom::Func(v, self.parse_args()?)),
                 _ => Expr::Atom(Atom::Const(v)),
             },
             Token::Lparen(_) => {
-                let expr = self.parse_expr(0.0);
-                return match self.consume()? {
+                let expr = self.parse_expr(0.0)?;
+                match self.consume()? {
                     Token::Rparen(_) => expr,
                     _ => {
                         return Result::Err(ParserErr::SyntaxError(
                             "expected ')' character".to_string(),
                         ))
                     }
-                };
+                }
             }
+            Token::Lbracket(_) => Expr::Atom(Atom::Vector(self.parse_items()?)),
             Token::Plus(_) => Expr::UnOp(UnOp::Pos, Box::new(self.parse_unary()?)),
             Token::Minus(_) => Expr::UnOp(UnOp::Neg, Box::new(self.parse_unary()?)),
             Token::Not(_) => Expr::UnOp(UnOp::Not, Box::new(self.parse_unary()?)),
             _ => return Result::Err(ParserErr::SyntaxError("SyntaxError".to_string())),
         };
+        lhs = self.parse_index(lhs)?;
 
         loop {
             let op = match self.peek()? {
@@ -75,12 +83,26 @@ where
                 Token::Div(_) => BinOp::Div,
                 Token::Mod(_) => BinOp::Mod,
                 Token::Pow(_) => BinOp::Pow,
+                Token::PlusMinus(_) => BinOp::PlusMinus,
                 Token::And(_) => BinOp::And,
                 Token::Or(_) => BinOp::Or,
                 Token::Xor(_) => BinOp::Xor,
+                Token::Eq(_) => BinOp::Eq,
+                Token::Assign(_) => BinOp::Equation,
+                Token::Ne(_) => BinOp::Ne,
+                Token::Lt(_) => BinOp::Lt,
+                Token::Le(_) => BinOp::Le,
+                Token::Gt(_) => BinOp::Gt,
+                Token::Ge(_) => BinOp::Ge,
+                Token::AndAnd(_) => BinOp::AndAnd,
+                Token::OrOr(_) => BinOp::OrOr,
+                Token::Shl(_) => BinOp::Shl,
+                Token::Shr(_) => BinOp::Shr,
+                Token::To(_) => BinOp::To,
                 Token::Eof(_) => break,
                 Token::Comma(_) => break,
                 Token::Rparen(_) => break,
+                Token::Rbracket(_) => break,
                 _ => {
                     return Result::Err(ParserErr::SyntaxError("expected operator".to_string()));
                 }
@@ -100,9 +122,11 @@ where
     }
 
     fn parse_int(&self, value: String, radix: u32) -> Result<Atom, ParserErr> {
-        match usize::from_str_radix(value.as_str(), radix) {
-            Result::Ok(v) => Result::Ok(Atom::Int(v as Integer)),
-            Result::Err(e) => Result::Err(ParserErr::ParseIntError(e.to_string())),
+        match Integer::parse_bytes(value.as_bytes(), radix) {
+            Option::Some(v) => Result::Ok(Atom::Int(v)),
+            Option::None => Result::Err(ParserErr::ParseIntError(
+                format!("invalid base {} integer '{}'", radix, value),
+            )),
         }
     }
 
@@ -113,6 +137,27 @@ where
         }
     }
 
+    // a number directly followed by a name carries it as a unit, as in
+    // `3 m` or `2 m**2`, or is multiplied by a call, as in `2 sin(x)`
+    fn parse_number(&mut self, atom: Atom) -> Result<Expr, ParserErr> {
+        let name = match self.peek()? {
+            Token::Name(v, _) => v,
+            _ => return Result::Ok(Expr::Atom(atom)),
+        };
+        self.consume()?;
+
+        let factor = match self.peek()? {
+            Token::Lparen(_) => Expr::Atom(Atom::Func(name, self.parse_args()?)),
+            Token::Pow(_) => {
+                self.consume()?;
+                let exp = self.parse_unary()?;
+                Expr::BinOp(BinOp::Pow, Box::new(Expr::Atom(Atom::Unit(name))), Box::new(exp))
+            }
+            _ => return Result::Ok(Expr::Atom(Atom::Quantity(Box::new(atom), name))),
+        };
+        Result::Ok(Expr::BinOp(BinOp::Mul, Box::new(Expr::Atom(atom)), Box::new(factor)))
+    }
+
     fn parse_args(&mut self) -> Result<Vec<Expr>, ParserErr> {
         match self.consume()? {
             Token::Lparen(_) => {
@@ -136,10 +181,51 @@ where
         }
     }
 
+    // the elements of a `[a, b, ...]` literal, after its opening bracket
+    fn parse_items(&mut self) -> Result<Vec<Expr>, ParserErr> {
+        let mut items: Vec<Expr> = Vec::new();
+        if let Token::Rbracket(_) = self.peek()? {
+            self.consume()?;
+            return Result::Ok(items);
+        }
+        loop {
+            items.push(self.parse_expr(0.0)?);
+            match self.consume()? {
+                Token::Comma(_) => continue,
+                Token::Rbracket(_) => break,
+                _ => return Result::Err(ParserErr::SyntaxError("expected ',' or ']'".to_string())),
+            }
+        }
+        Result::Ok(items)
+    }
+
+    fn parse_index(&mut self, mut expr: Expr) -> Result<Expr, ParserErr> {
+        while let Token::Lbracket(_) = self.peek()? {
+            self.consume()?;
+            let index = self.parse_expr(0.0)?;
+            match self.consume()? {
+                Token::Rbracket(_) => expr = Expr::Index(Box::new(expr), Box::new(index)),
+                _ => return Result::Err(ParserErr::SyntaxError("expected ']'".to_string())),
+            }
+        }
+        Result::Ok(expr)
+    }
+
     fn parse_unary(&mut self) -> Result<Expr, ParserErr> {
+        let expr = self.parse_operand()?;
+        self.parse_index(expr)
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr, ParserErr> {
         match self.consume()? {
-            Token::Int(v, radix, _) => Result::Ok(Expr::Atom(self.parse_int(v, radix)?)),
-            Token::Float(v, _) => Result::Ok(Expr::Atom(self.parse_float(v)?)),
+            Token::Int(v, radix, _) => {
+                let atom = self.parse_int(v, radix)?;
+                self.parse_number(atom)
+            }
+            Token::Float(v, _) => {
+                let atom = self.parse_float(v)?;
+                self.parse_number(atom)
+            }
             Token::Name(v, _) => match self.peek()? {
                 Token::Lparen(_) => Result::Ok(Expr::Atom(Atom::Func(v, self.parse_args()?))),
                 _ => Result::Ok(Expr::Atom(Atom::Const(v))),
@@ -151,6 +237,7 @@ where
                     _ => Result::Err(ParserErr::SyntaxError("expected ')'".to_string())),
                 }
             }
+            Token::Lbracket(_) => Result::Ok(Expr::Atom(Atom::Vector(self.parse_items()?))),
             Token::Plus(_) => Result::Ok(Expr::UnOp(UnOp::Pos, Box::new(self.parse_unary()?))),
             Token::Minus(_) => Result::Ok(Expr::UnOp(UnOp::Neg, Box::new(self.parse_unary()?))),
             Token::Not(_) => Result::Ok(Expr::UnOp(UnOp::Not, Box::new(self.parse_unary()?))),
@@ -161,13 +248,6 @@ where
     }
 
     fn infix_binding_power(&self, op: BinOp) -> (f32, f32) {
-        match op {
-            BinOp::Xor => (1.0, 1.1),
-            BinOp::Or => (2.0, 2.1),
-            BinOp::And => (3.0, 3.1),
-            BinOp::Plus | BinOp::Minus => (4.0, 4.1),
-            BinOp::Mul | BinOp::Div | BinOp::Mod => (5.0, 5.1),
-            BinOp::Pow => (6.1, 6.0),
-        }
+        op.binding_power()
     }
 }
@@ -0,0 +1,338 @@
+use crate::calc::common::{Complex, Float, Integer, Rational};
+use crate::calc::context::Context;
+use crate::calc::eval::{eval, EvalErr};
+use crate::calc::fraction::{best_approximation, DEFAULT_MAX_DENOMINATOR};
+use crate::calc::matrix::Matrix;
+use crate::calc::nodes::{Atom, BinOp, Expr, UnOp};
+use crate::calc::symbolic;
+use crate::calc::value::Value;
+use num_traits::ToPrimitive;
+
+// coefficients are listed from the highest power down, so `[1, 0, -2]`
+// stands for `x**2 - 2`
+
+// highest degree a polynomial may be expanded or fitted to
+pub const MAX_DEGREE: usize = 1024;
+
+// most rounds of the Aberth iteration before giving up
+const MAX_ITERATIONS: usize = 500;
+
+// roots this close to the real axis, relative to their size, are taken as
+// real when the coefficients are; multiple roots only settle to a fraction
+// of the digits of a float
+const REAL_TOLERANCE: Float = 1e-6;
+
+// parts of a root this small next to its size are rounding error
+const NOISE: Float = 1e-14;
+
+// how far off a numerical root may be for a nearby fraction to be tried
+const ROUGH_TOLERANCE: Float = 1e-3;
+
+pub fn multiply(a: &[Value], b: &[Value], ctx: &Context) -> Result<Vec<Value>, EvalErr> {
+    if a.is_empty() || b.is_empty() {
+        return Result::Ok(Vec::new());
+    }
+    let mut product: Vec<Option<Value>> = vec![Option::None; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            let term = x.clone().mul(y.clone(), ctx)?;
+            product[i + j] = Option::Some(match product[i + j].take() {
+                Option::Some(total) => total.add(term, ctx)?,
+                Option::None => term,
+            });
+        }
+    }
+    Result::Ok(product.into_iter().flatten().collect())
+}
+
+// the value of `p` at `x` by Horner's rule
+pub fn evaluate(p: &[Value], x: &Value, ctx: &Context) -> Result<Value, EvalErr> {
+    let Option::Some((first, rest)) = p.split_first() else {
+        return Result::Ok(Value::zero());
+    };
+    let mut total = first.clone();
+    for c in rest {
+        total = total.mul(x.clone(), ctx)?.add(c.clone(), ctx)?;
+    }
+    Result::Ok(total)
+}
+
+// the polynomial of the given degree closest to the points `(xs, ys)` in
+// the least squares sense, from the normal equations so that exact points
+// give an exact fit
+pub fn fit(xs: &[Value], ys: &[Value], degree: usize, ctx: &Context) -> Result<Vec<Value>, EvalErr> {
+    if xs.len() != ys.len() {
+        return Result::Err(EvalErr::LengthMismatch(format!(
+            "{} x values and {} y values", xs.len(), ys.len()
+        )));
+    }
+    if degree >= xs.len() || degree > MAX_DEGREE {
+        return Result::Err(EvalErr::InvalidArgument(format!(
+            "a fit of degree {} needs more than {} points", degree, xs.len()
+        )));
+    }
+    let rows = xs
+        .iter()
+        .map(|x| (0..=degree).rev().map(|k| x.clone().pow(Value::Int(Integer::from(k)), ctx)).collect())
+        .collect::<Result<Vec<Vec<Value>>, _>>()?;
+    let too_few = || EvalErr::InvalidArgument("expected at least one point".to_string());
+    let v = Matrix::from_rows(rows).ok_or_else(too_few)?;
+    let y = Matrix::column(ys.to_vec()).ok_or_else(too_few)?;
+    let vt = v.transpose();
+    let normal = vt.mul(&v, ctx)?;
+    let rhs = vt.mul(&y, ctx)?;
+    Result::Ok(normal.solve(&rhs, ctx)?.into_items())
+}
+
+// every root of `p`, repeated by multiplicity; zero and linear factors are
+// solved exactly, the rest numerically
+pub fn roots(p: &[Value], ctx: &Context) -> Result<Vec<Value>, EvalErr> {
+    for c in p {
+        number(c)?;
+    }
+    let Option::Some(lead) = p.iter().position(|c| !c.is_exact_zero()) else {
+        return Result::Err(EvalErr::InvalidArgument(
+            "every number is a root of the zero polynomial".to_string(),
+        ));
+    };
+    let p = &p[lead..];
+    let zeros = p.iter().rev().take_while(|c| c.is_exact_zero()).count();
+    let p = &p[..p.len() - zeros];
+
+    let mut result = vec![Value::zero(); zeros];
+    match p {
+        [_] => {},
+        [a, b] => result.push(b.clone().neg(ctx)?.div(a.clone(), ctx)?),
+        _ => {
+            let coefficients = p.iter().map(number).collect::<Result<Vec<_>, _>>()?;
+            let real = coefficients.iter().all(|c| c.im == 0.0);
+            let exact = p.iter().all(|c| c.as_ratio().is_some());
+            for z in aberth(&coefficients)? {
+                let root = if exact { exact_root(p, z, ctx) } else { Option::None };
+                result.push(root.unwrap_or_else(|| {
+                    if real && z.im.abs() <= REAL_TOLERANCE * z.norm().max(1.0) {
+                        return Value::Float(z.re);
+                    }
+                    let clean = |x: Float| if x.abs() <= NOISE * z.norm() { 0.0 } else { x };
+                    Value::from_complex(Complex::new(clean(z.re), clean(z.im)))
+                }));
+            }
+        },
+    }
+    result.sort_by(|a, b| {
+        let (a, b) = (a.as_complex(), b.as_complex());
+        a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im))
+    });
+    Result::Ok(result)
+}
+
+// the coefficients of `expr` as a polynomial in the variable `var`
+pub fn coefficients(ctx: &Context, expr: &Expr, var: &str) -> Result<Vec<Value>, EvalErr> {
+    let mut ascending = expand(ctx, &symbolic::with_variable(expr, var), var)?;
+    while ascending.len() > 1 && ascending.last().is_some_and(Value::is_exact_zero) {
+        ascending.pop();
+    }
+    ascending.reverse();
+    Result::Ok(ascending)
+}
+
+// coefficients from the constant term up, which lines up sums by index
+fn expand(ctx: &Context, expr: &Expr, var: &str) -> Result<Vec<Value>, EvalErr> {
+    if !symbolic::depends_on(expr, var) {
+        return Result::Ok(vec![eval(ctx, expr)?.into_si()]);
+    }
+    let not_polynomial = || EvalErr::InvalidArgument(format!("{} is not a polynomial in {}", expr, var));
+    let terms = match expr {
+        Expr::Atom(Atom::Const(_)) => vec![Value::zero(), Value::Int(Integer::from(1))],
+        Expr::UnOp(UnOp::Pos, e) => expand(ctx, e, var)?,
+        Expr::UnOp(UnOp::Neg, e) => negate(expand(ctx, e, var)?, ctx)?,
+        Expr::BinOp(BinOp::Plus, a, b) => add(expand(ctx, a, var)?, expand(ctx, b, var)?, ctx)?,
+        Expr::BinOp(BinOp::Minus, a, b) => add(expand(ctx, a, var)?, negate(expand(ctx, b, var)?, ctx)?, ctx)?,
+        Expr::BinOp(BinOp::Mul, a, b) => multiply(&expand(ctx, a, var)?, &expand(ctx, b, var)?, ctx)?,
+        Expr::BinOp(BinOp::Div, a, b) if !symbolic::depends_on(b, var) => {
+            let divisor = eval(ctx, b)?.into_si();
+            expand(ctx, a, var)?
+                .into_iter()
+                .map(|c| c.div(divisor.clone(), ctx))
+                .collect::<Result<Vec<_>, _>>()?
+        },
+        Expr::BinOp(BinOp::Pow, a, b) if !symbolic::depends_on(b, var) => {
+            let base = expand(ctx, a, var)?;
+            let exponent = match eval(ctx, b)?.into_si() {
+                Value::Int(n) => n.to_usize().ok_or_else(not_polynomial)?,
+                _ => return Result::Err(not_polynomial()),
+            };
+            if exponent.saturating_mul(base.len().saturating_sub(1)) > MAX_DEGREE {
+                return Result::Err(EvalErr::Overflow(format!("{} has a degree above {}", expr, MAX_DEGREE)));
+            }
+            let mut power = vec![Value::Int(Integer::from(1))];
+            for _ in 0..exponent {
+                power = multiply(&power, &base, ctx)?;
+            }
+            power
+        },
+        _ => return Result::Err(not_polynomial()),
+    };
+    if terms.len() > MAX_DEGREE + 1 {
+        return Result::Err(EvalErr::Overflow(format!("{} has a degree above {}", expr, MAX_DEGREE)));
+    }
+    Result::Ok(terms)
+}
+
+fn add(a: Vec<Value>, b: Vec<Value>, ctx: &Context) -> Result<Vec<Value>, EvalErr> {
+    let (mut long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    for (i, c) in short.into_iter().enumerate() {
+        long[i] = long[i].clone().add(c, ctx)?;
+    }
+    Result::Ok(long)
+}
+
+fn negate(p: Vec<Value>, ctx: &Context) -> Result<Vec<Value>, EvalErr> {
+    p.into_iter().map(|c| c.neg(ctx)).collect()
+}
+
+// a rational root near `z` that makes the exact polynomial vanish; the
+// check is exact, so even the rough estimates of repeated roots will do
+fn exact_root(p: &[Value], z: Complex, ctx: &Context) -> Option<Value> {
+    if z.im.abs() > ROUGH_TOLERANCE * z.norm().max(1.0) {
+        return Option::None;
+    }
+    let r = best_approximation(&Rational::from_float(z.re)?, &Integer::from(DEFAULT_MAX_DENOMINATOR));
+    let root = Value::from_ratio(r);
+    match evaluate(p, &root, ctx) {
+        Result::Ok(v) if v.is_exact_zero() => Option::Some(root),
+        _ => Option::None,
+    }
+}
+
+fn number(v: &Value) -> Result<Complex, EvalErr> {
+    match v {
+        Value::Int(_) | Value::Float(_) | Value::Rational(_) | Value::BigFloat(_) | Value::Complex(_) => {
+            Result::Ok(v.as_complex())
+        },
+        v => Result::Err(EvalErr::TypeMismatch(format!("expected number coefficient, got {}", v))),
+    }
+}
+
+// the Aberth-Ehrlich method, which refines guesses for all the roots at
+// once while pushing each away from the others
+fn aberth(p: &[Complex]) -> Result<Vec<Complex>, EvalErr> {
+    let n = p.len() - 1;
+    let monic: Vec<Complex> = p.iter().map(|c| c / p[0]).collect();
+    // the guesses are spread on a circle at the geometric mean of the root
+    // moduli, or within Cauchy's bound when zero is a root, turned off the
+    // real axis so conjugate pairs can separate
+    let radius = match monic[n].norm() {
+        0.0 => 1.0 + monic[1..].iter().map(|c| c.norm()).fold(0.0, Float::max),
+        product => product.powf(1.0 / n as Float),
+    };
+    let mut z: Vec<Complex> = (0..n)
+        .map(|k| Complex::from_polar(radius, 2.0 * std::f64::consts::PI * k as Float / n as Float + 0.4))
+        .collect();
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut settled = true;
+        for i in 0..n {
+            // the value is lost in rounding error, so `z[i]` is as good as it gets
+            let Option::Some(ratio) = log_derivative(&monic, z[i]) else {
+                continue;
+            };
+            let repulsion: Complex = (0..n).filter(|&j| j != i).map(|j| (z[i] - z[j]).finv()).sum();
+            let step = (ratio - repulsion).finv();
+            if !step.re.is_finite() || !step.im.is_finite() {
+                settled = false;
+                continue;
+            }
+            z[i] -= step;
+            if step.norm() > 4.0 * Float::EPSILON * z[i].norm() {
+                settled = false;
+            }
+        }
+        if settled {
+            return Result::Ok(z);
+        }
+    }
+    Result::Err(EvalErr::ToleranceNotMet(format!("roots did not settle, last at {:?}", z)))
+}
+
+// p'(z) / p(z), or None when p(z) is within rounding error of zero. outside
+// the unit circle this goes through the reversed polynomial in 1 / z, as
+// z**n overflows long before the maximum degree
+fn log_derivative(p: &[Complex], z: Complex) -> Option<Complex> {
+    if z.norm() <= 1.0 {
+        let (value, slope, error) = horner(p, z);
+        return (value.norm() > error).then(|| slope.fdiv(value));
+    }
+    // p(z) = z**n q(w) and p'(z) = z**(n - 1) (n q(w) - w q'(w)) with w = 1 / z
+    let reversed: Vec<Complex> = p.iter().rev().copied().collect();
+    let w = z.inv();
+    let (value, slope, error) = horner(&reversed, w);
+    let n = (p.len() - 1) as Float;
+    (value.norm() > error).then(|| w * (n - w * slope.fdiv(value)))
+}
+
+// the value and slope of `p` at `z`, and a bound on the rounding error in
+// the value
+fn horner(p: &[Complex], z: Complex) -> (Complex, Complex, Float) {
+    let mut value = p[0];
+    let mut slope = Complex::new(0.0, 0.0);
+    let mut error = p[0].norm();
+    for c in &p[1..] {
+        slope = slope * z + value;
+        value = value * z + c;
+        error = error * z.norm() + c.norm();
+    }
+    (value, slope, 4.0 * Float::EPSILON * error * p.len() as Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(p: &[Float]) -> Vec<Complex> {
+        let p: Vec<Complex> = p.iter().map(|&c| Complex::new(c, 0.0)).collect();
+        let mut z = aberth(&p).unwrap();
+        z.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        z
+    }
+
+    fn close(a: Complex, b: Complex, tolerance: Float) -> bool {
+        (a - b).norm() < tolerance
+    }
+
+    #[test]
+    fn simple_roots() {
+        let z = sorted(&[1.0, -6.0, 11.0, -6.0]);
+        for (z, r) in z.iter().zip([1.0, 2.0, 3.0]) {
+            assert!(close(*z, Complex::new(r, 0.0), 1e-12));
+        }
+        let z = sorted(&[1.0, 0.0, 0.0, 0.0, 1.0]);
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        for r in [Complex::new(-h, -h), Complex::new(-h, h), Complex::new(h, -h), Complex::new(h, h)] {
+            assert!(z.iter().any(|z| close(*z, r, 1e-12)));
+        }
+    }
+
+    #[test]
+    fn roots_of_unity() {
+        for n in [700, MAX_DEGREE] {
+            let mut p = vec![0.0; n + 1];
+            p[0] = 1.0;
+            p[n] = -1.0;
+            let z = sorted(&p);
+            assert_eq!(z.len(), n);
+            assert!(z.iter().all(|z| (z.norm() - 1.0).abs() < 1e-12));
+            assert!(close(z[n - 1], Complex::new(1.0, 0.0), 1e-12));
+        }
+    }
+
+    #[test]
+    fn repeated_roots() {
+        // (x - 1)**3 (x + 2)
+        let z = sorted(&[1.0, -1.0, -3.0, 5.0, -2.0]);
+        assert!(close(z[0], Complex::new(-2.0, 0.0), 1e-10));
+        for z in &z[1..] {
+            assert!(close(*z, Complex::new(1.0, 0.0), 1e-4));
+        }
+    }
+}
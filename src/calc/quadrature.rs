@@ -0,0 +1,160 @@
+use crate::calc::common::Float;
+use crate::calc::eval::EvalErr;
+
+// tolerance used by `integrate` unless one is given, both absolute and
+// relative to the size of the integral
+pub const DEFAULT_TOLERANCE: Float = 1e-10;
+
+// most pieces the range is split into before giving up
+const MAX_INTERVALS: usize = 1000;
+
+// abscissae and weights of the 15-point Kronrod rule, from the centre
+// outwards, and of the 7-point Gauss rule embedded in it
+const XGK: [Float; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+const WGK: [Float; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+const WG: [Float; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+type Integrand<'a> = dyn FnMut(Float) -> Result<Float, EvalErr> + 'a;
+
+// a change of variable, giving the point of the original range for `t`
+// and the factor `dx/dt`
+type Mapping = fn(Float, Float, Float) -> (Float, Float);
+
+// the integral of `f` from `a` to `b`; infinite bounds are mapped onto a
+// finite range by a change of variable
+pub fn integrate<F>(mut f: F, a: Float, b: Float, tolerance: Float) -> Result<Float, EvalErr>
+where
+    F: FnMut(Float) -> Result<Float, EvalErr>,
+{
+    if a.is_nan() || b.is_nan() {
+        return Result::Err(EvalErr::InvalidArgument(format!("expected bounds, got {} and {}", a, b)));
+    }
+    if a == b {
+        return Result::Ok(0.0);
+    }
+    if a > b {
+        return integrate(f, b, a, tolerance).map(|v| -v);
+    }
+    let (map, lo, hi): (Mapping, Float, Float) = match (a.is_finite(), b.is_finite()) {
+        (true, true) => (|t, _, _| (t, 1.0), a, b),
+        (true, false) => (|t, a, _| (a + t / (1.0 - t), 1.0 / ((1.0 - t) * (1.0 - t))), 0.0, 1.0),
+        (false, true) => (|t, _, b| (b - (1.0 - t) / t, 1.0 / (t * t)), 0.0, 1.0),
+        (false, false) => (|t, _, _| (t / (1.0 - t * t), (1.0 + t * t) / ((1.0 - t * t) * (1.0 - t * t))), -1.0, 1.0),
+    };
+    let mut g = |t| {
+        let (x, weight) = map(t, a, b);
+        let y = f(x)?;
+        if !y.is_finite() {
+            return Result::Err(EvalErr::NonFiniteResult(format!("integrand is {} at {}", y, x)));
+        }
+        if y == 0.0 {
+            return Result::Ok(0.0);
+        }
+        let y = y * weight;
+        if !y.is_finite() {
+            return Result::Err(EvalErr::NonFiniteResult(format!("integral diverges near {}", x)));
+        }
+        Result::Ok(y)
+    };
+    adapt(&mut g, lo, hi, tolerance)
+}
+
+// global adaptive quadrature: the piece with the largest error estimate is
+// halved until the estimates add up to less than the tolerance
+fn adapt(f: &mut Integrand, a: Float, b: Float, tolerance: Float) -> Result<Float, EvalErr> {
+    let (value, error) = gauss_kronrod(f, a, b)?;
+    let mut pieces = vec![(a, b, value, error)];
+    loop {
+        let value: Float = pieces.iter().map(|p| p.2).sum();
+        let error: Float = pieces.iter().map(|p| p.3).sum();
+        if error <= tolerance.max(tolerance * value.abs()) {
+            return Result::Ok(value);
+        }
+        if pieces.len() >= MAX_INTERVALS {
+            return Result::Err(EvalErr::ToleranceNotMet(format!(
+                "integral is about {} with an estimated error of {}", value, error
+            )));
+        }
+
+        let worst = (0..pieces.len()).max_by(|&i, &j| pieces[i].3.total_cmp(&pieces[j].3)).unwrap_or(0);
+        let (a, b, _, _) = pieces.swap_remove(worst);
+        let mid = (a + b) / 2.0;
+        let (left, left_error) = gauss_kronrod(f, a, mid)?;
+        let (right, right_error) = gauss_kronrod(f, mid, b)?;
+        pieces.push((a, mid, left, left_error));
+        pieces.push((mid, b, right, right_error));
+    }
+}
+
+// the 15-point Kronrod estimate over `[a, b]` and its difference from the
+// 7-point Gauss estimate as the error
+fn gauss_kronrod(f: &mut Integrand, a: Float, b: Float) -> Result<(Float, Float), EvalErr> {
+    let centre = (a + b) / 2.0;
+    let half = (b - a) / 2.0;
+    let fc = f(centre)?;
+    let mut kronrod = fc * WGK[7];
+    let mut gauss = fc * WG[3];
+    for j in 0..7 {
+        let dx = half * XGK[j];
+        let pair = f(centre - dx)? + f(centre + dx)?;
+        kronrod += WGK[j] * pair;
+        if j % 2 == 1 {
+            gauss += WG[j / 2] * pair;
+        }
+    }
+    Result::Ok((kronrod * half, ((kronrod - gauss) * half).abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Float, b: Float) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn finite_ranges() {
+        assert!(close(integrate(|x| Result::Ok(x * x), 0.0, 3.0, DEFAULT_TOLERANCE).unwrap(), 9.0));
+        assert!(close(integrate(|x| Result::Ok(x.sin()), std::f64::consts::PI, 0.0, DEFAULT_TOLERANCE).unwrap(), -2.0));
+        assert!(close(integrate(|x| Result::Ok(1.0 / x.sqrt()), 0.0, 1.0, DEFAULT_TOLERANCE).unwrap(), 2.0));
+    }
+
+    #[test]
+    fn infinite_ranges() {
+        let gauss = |x: Float| Result::Ok((-x * x).exp());
+        let root_pi = std::f64::consts::PI.sqrt();
+        assert!(close(integrate(gauss, Float::NEG_INFINITY, Float::INFINITY, DEFAULT_TOLERANCE).unwrap(), root_pi));
+        assert!(close(integrate(gauss, 0.0, Float::INFINITY, DEFAULT_TOLERANCE).unwrap(), root_pi / 2.0));
+        assert!(close(integrate(|x| Result::Ok(x.exp()), Float::NEG_INFINITY, 0.0, DEFAULT_TOLERANCE).unwrap(), 1.0));
+    }
+
+    #[test]
+    fn unmet_tolerance() {
+        let result = integrate(|x| Result::Ok(1.0 / x), 0.0, 1.0, DEFAULT_TOLERANCE);
+        assert!(matches!(result, Result::Err(EvalErr::ToleranceNotMet(_))));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::calc::common::Float;
+use crate::calc::eval::EvalErr;
+
+// a root is accepted once it is known to within this much, plus a few
+// units in the last place of its size
+const TOLERANCE: Float = 1e-15;
+
+// most steps either method takes before giving up
+const MAX_ITERATIONS: usize = 200;
+
+type Function<'a> = dyn FnMut(Float) -> Result<Float, EvalErr> + 'a;
+
+// a root of `f` near `guess` by Newton's method, using the derivative `df`
+// when there is one; should that fail to converge, a range around `guess`
+// is widened until `f` changes sign and searched with Brent's method
+pub fn near(f: &mut Function, df: Option<&mut Function>, guess: Float) -> Result<Float, EvalErr> {
+    if !guess.is_finite() {
+        return Result::Err(EvalErr::InvalidArgument(format!("expected finite guess, got {}", guess)));
+    }
+    finite(guess, f(guess)?)?;
+    if let Option::Some(root) = newton(f, df, guess) {
+        return Result::Ok(root);
+    }
+    match bracket(f, guess) {
+        Option::Some((a, b)) => between(f, a, b),
+        Option::None => Result::Err(EvalErr::ToleranceNotMet(format!("no root found near {}", guess))),
+    }
+}
+
+// a root of `f` between `a` and `b` by Brent's method, which needs `f` to
+// change sign over the range
+pub fn between(f: &mut Function, a: Float, b: Float) -> Result<Float, EvalErr> {
+    if !a.is_finite() || !b.is_finite() {
+        return Result::Err(EvalErr::InvalidArgument(format!("expected finite bounds, got {} and {}", a, b)));
+    }
+    let (mut a, mut b) = (a, b);
+    let (mut fa, mut fb) = (finite(a, f(a)?)?, finite(b, f(b)?)?);
+    if fa == 0.0 {
+        return Result::Ok(a);
+    }
+    if fb == 0.0 {
+        return Result::Ok(b);
+    }
+    if fa.signum() == fb.signum() {
+        return Result::Err(EvalErr::InvalidArgument(format!(
+            "no sign change between {} and {}, where the values are {} and {}", a, b, fa, fb
+        )));
+    }
+    let bound = fa.abs().max(fb.abs());
+
+    // `b` is the best estimate so far and `c` the other end of the range
+    // holding the root; `d` is the last step and `e` the one before
+    let (mut c, mut fc) = (a, fa);
+    let mut d = b - a;
+    let mut e = d;
+    for _ in 0..MAX_ITERATIONS {
+        if fb.signum() == fc.signum() {
+            (c, fc) = (a, fa);
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+
+        let tolerance = 2.0 * Float::EPSILON * b.abs() + TOLERANCE / 2.0;
+        let m = (c - b) / 2.0;
+        if m.abs() <= tolerance || fb == 0.0 {
+            // a sign change across a pole narrows down just like a root
+            if fb.abs() > bound {
+                return Result::Err(EvalErr::ToleranceNotMet(format!(
+                    "sign change near {} is a discontinuity, not a root", b
+                )));
+            }
+            return Result::Ok(b);
+        }
+
+        // interpolate when the last steps shrank fast enough, else bisect
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * m * s, 1.0 - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+                (s * (2.0 * m * q * (q - r) - (b - a) * (r - 1.0)), (q - 1.0) * (r - 1.0) * (s - 1.0))
+            };
+            if p > 0.0 {
+                q = -q;
+            } else {
+                p = -p;
+            }
+            if 2.0 * p < (3.0 * m * q - (tolerance * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = m;
+                e = m;
+            }
+        } else {
+            d = m;
+            e = m;
+        }
+
+        (a, fa) = (b, fb);
+        b += if d.abs() > tolerance { d } else { tolerance.copysign(m) };
+        fb = finite(b, f(b)?)?;
+    }
+    Result::Err(EvalErr::ToleranceNotMet(format!("no root found between {} and {}", a, b)))
+}
+
+// None when the iteration fails to settle, hits a flat spot or leaves the
+// domain of `f`
+fn newton(f: &mut Function, mut df: Option<&mut Function>, guess: Float) -> Option<Float> {
+    let mut x = guess;
+    for _ in 0..MAX_ITERATIONS {
+        let y = f(x).ok()?;
+        if y == 0.0 {
+            return Option::Some(x);
+        }
+        let slope = match df {
+            Option::Some(ref mut df) => df(x).ok()?,
+            Option::None => central_difference(f, x).ok()?,
+        };
+        let step = y / slope;
+        if !step.is_finite() {
+            return Option::None;
+        }
+        x -= step;
+        if step.abs() <= TOLERANCE + 4.0 * Float::EPSILON * x.abs() {
+            return Option::Some(x);
+        }
+    }
+    Option::None
+}
+
+fn central_difference(f: &mut Function, x: Float) -> Result<Float, EvalErr> {
+    let h = Float::EPSILON.cbrt() * x.abs().max(1.0);
+    Result::Ok((f(x + h)? - f(x - h)?) / (2.0 * h))
+}
+
+// the first range around `x`, growing by doubling steps on either side,
+// over which `f` changes sign
+fn bracket(f: &mut Function, x: Float) -> Option<(Float, Float)> {
+    let y = f(x).ok()?;
+    let mut step = 0.01 * x.abs().max(1.0);
+    // the last point reached on each side, until `f` fails there
+    let mut left = Option::Some((x, y));
+    let mut right = Option::Some((x, y));
+    for _ in 0..MAX_ITERATIONS {
+        if let Option::Some((a, fa)) = left {
+            left = f(x - step).ok().filter(|v| v.is_finite()).map(|v| (x - step, v));
+            if let Option::Some((b, fb)) = left {
+                if fa.signum() != fb.signum() {
+                    return Option::Some((b, a));
+                }
+            }
+        }
+        if let Option::Some((a, fa)) = right {
+            right = f(x + step).ok().filter(|v| v.is_finite()).map(|v| (x + step, v));
+            if let Option::Some((b, fb)) = right {
+                if fa.signum() != fb.signum() {
+                    return Option::Some((a, b));
+                }
+            }
+        }
+        if left.is_none() && right.is_none() {
+            return Option::None;
+        }
+        step *= 2.0;
+    }
+    Option::None
+}
+
+fn finite(x: Float, y: Float) -> Result<Float, EvalErr> {
+    if !y.is_finite() {
+        return Result::Err(EvalErr::NonFiniteResult(format!("function is {} at {}", y, x)));
+    }
+    Result::Ok(y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Float, b: Float) -> bool {
+        (a - b).abs() < 1e-10
+    }
+
+    #[test]
+    fn newton_steps() {
+        let root = near(&mut |x| Result::Ok(x * x - 2.0), Option::Some(&mut |x| Result::Ok(2.0 * x)), 1.0);
+        assert!(close(root.unwrap(), std::f64::consts::SQRT_2));
+        // the slope is estimated when there is no derivative
+        assert!(close(near(&mut |x| Result::Ok(x.cos() - x), Option::None, 0.0).unwrap(), 0.7390851332151607));
+        // Newton's method cycles between 0 and 1 here, so the fallback finds it
+        let root = near(&mut |x| Result::Ok(x * x * x - 2.0 * x + 2.0), Option::None, 0.0);
+        assert!(close(root.unwrap(), -1.7692923542386314));
+    }
+
+    #[test]
+    fn bracketed_search() {
+        assert!(close(between(&mut |x| Result::Ok(x * x * x - x - 1.0), 1.0, 2.0).unwrap(), 1.324717957244746));
+        assert!(close(between(&mut |x| Result::Ok(x.sin()), 3.0, 4.0).unwrap(), std::f64::consts::PI));
+        assert!(between(&mut |x| Result::Ok(x * x + 1.0), -1.0, 1.0).is_err());
+        assert!(between(&mut |x| Result::Ok(1.0 / x), -1.0, 2.0).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::calc::common::Float;
+
+// Neumaier's compensated sum, which carries the low-order bits each
+// addition drops into a separate correction term
+pub fn sum<I>(xs: I) -> Float
+where
+    I: IntoIterator<Item = Float>,
+{
+    let (mut total, mut correction) = (0.0, 0.0);
+    for x in xs {
+        let t = total + x;
+        if total.abs() >= x.abs() {
+            correction += (total - t) + x;
+        } else {
+            correction += (x - t) + total;
+        }
+        total = t;
+    }
+    total + correction
+}
+
+// the size and central moments of a sample; the deviations are taken
+// from a mean found first, so no large sums cancel, and added with `sum`
+#[derive(Clone, Copy)]
+pub struct Moments {
+    n: Float,
+    m2: Float,
+    m3: Float,
+    m4: Float,
+}
+
+impl Moments {
+    pub fn of(xs: &[Float]) -> Moments {
+        let n = xs.len() as Float;
+        let mean = sum(xs.iter().copied()) / n;
+        let moment = |k: i32| sum(xs.iter().map(|x| (x - mean).powi(k)));
+        Moments { n, m2: moment(2), m3: moment(3), m4: moment(4) }
+    }
+
+    // divided by `n - 1` for a sample, `n` for a whole population
+    pub fn variance(&self, sample: bool) -> Float {
+        let dof = if sample { self.n - 1.0 } else { self.n };
+        self.m2 / dof
+    }
+
+    pub fn skewness(&self) -> Float {
+        self.n.sqrt() * self.m3 / self.m2.powf(1.5)
+    }
+
+    // excess kurtosis, which is zero for a normal distribution
+    pub fn kurtosis(&self) -> Float {
+        self.n * self.m4 / (self.m2 * self.m2) - 3.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Float, b: Float) -> bool {
+        (a - b).abs() < 1e-12 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn compensated_sum() {
+        assert_eq!(sum(vec![0.1; 10]), 1.0);
+        assert_eq!(sum([1e100, 1.0, -1e100]), 1.0);
+        assert_eq!(sum(Vec::new()), 0.0);
+    }
+
+    #[test]
+    fn central_moments() {
+        let m = Moments::of(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert!(close(m.variance(false), 4.0));
+        assert!(close(m.variance(true), 32.0 / 7.0));
+        assert!(close(m.skewness(), 0.65625));
+        assert!(close(m.kurtosis(), -0.21875));
+        // a large offset does not swamp the spread
+        let m = Moments::of(&[1e9 + 4.0, 1e9 + 7.0, 1e9 + 13.0, 1e9 + 16.0]);
+        assert!(close(m.variance(true), 30.0));
+    }
+}
@@ -0,0 +1,458 @@
+use crate::calc::common::{Integer, Rational};
+use crate::calc::context::Context;
+use crate::calc::eval::EvalErr;
+use crate::calc::nodes::{Atom, BinOp, Expr, UnOp};
+use num_traits::{Signed, ToPrimitive, Zero};
+
+// largest power, in bits, that is folded into a single integer
+const MAX_FOLDED_BITS: u64 = 1024;
+
+// the derivative of `expr` with respect to the variable `var`, simplified
+pub fn derivative(expr: &Expr, var: &str) -> Result<Expr, EvalErr> {
+    Result::Ok(simplify(derive(&with_variable(expr, var), var)?))
+}
+
+// whether the variable `var` appears anywhere in `expr`
+pub fn depends_on(expr: &Expr, var: &str) -> bool {
+    match expr {
+        Expr::UnOp(_, e) => depends_on(e, var),
+        Expr::BinOp(_, lhs, rhs) | Expr::Index(lhs, rhs) => depends_on(lhs, var) || depends_on(rhs, var),
+        Expr::Atom(Atom::Const(name)) | Expr::Atom(Atom::Unit(name)) | Expr::Atom(Atom::Quantity(_, name)) => {
+            name.eq_ignore_ascii_case(var)
+        },
+        Expr::Atom(Atom::Func(_, args)) | Expr::Atom(Atom::Vector(args)) => args.iter().any(|e| depends_on(e, var)),
+        Expr::Atom(_) => false,
+    }
+}
+
+// `expr` with every use of the variable `var` replaced by `value`
+pub fn substitute(expr: &Expr, var: &str, value: &Expr) -> Expr {
+    match bind(expr, var) {
+        Expr::Atom(Atom::Const(name)) if name.eq_ignore_ascii_case(var) => value.clone(),
+        Expr::UnOp(op, e) => Expr::UnOp(op, Box::new(substitute(&e, var, value))),
+        Expr::BinOp(op, lhs, rhs) => {
+            Expr::BinOp(op, Box::new(substitute(&lhs, var, value)), Box::new(substitute(&rhs, var, value)))
+        },
+        Expr::Index(target, index) => {
+            Expr::Index(Box::new(substitute(&target, var, value)), Box::new(substitute(&index, var, value)))
+        },
+        Expr::Atom(Atom::Func(name, args)) => {
+            Expr::Atom(Atom::Func(name, args.iter().map(|e| substitute(e, var, value)).collect()))
+        },
+        Expr::Atom(Atom::Vector(items)) => {
+            Expr::Atom(Atom::Vector(items.iter().map(|e| substitute(e, var, value)).collect()))
+        },
+        e => e,
+    }
+}
+
+// rebuilds `expr` bottom up, folding integer constants and dropping terms
+// that add zero or multiply by one
+pub fn simplify(expr: Expr) -> Expr {
+    match expr {
+        Expr::UnOp(UnOp::Neg, e) => neg(simplify(*e)),
+        Expr::UnOp(UnOp::Pos, e) => simplify(*e),
+        Expr::UnOp(op, e) => Expr::UnOp(op, Box::new(simplify(*e))),
+        Expr::BinOp(op, lhs, rhs) => {
+            let (lhs, rhs) = (simplify(*lhs), simplify(*rhs));
+            match op {
+                BinOp::Plus => add(lhs, rhs),
+                BinOp::Minus => sub(lhs, rhs),
+                BinOp::Mul => mul(lhs, rhs),
+                BinOp::Div => div(lhs, rhs),
+                BinOp::Pow => pow(lhs, rhs),
+                op => Expr::BinOp(op, Box::new(lhs), Box::new(rhs)),
+            }
+        },
+        Expr::Index(target, index) => Expr::Index(Box::new(simplify(*target)), Box::new(simplify(*index))),
+        Expr::Atom(Atom::Func(name, args)) => call(&name, args.into_iter().map(simplify).collect()),
+        Expr::Atom(Atom::Vector(items)) => Expr::Atom(Atom::Vector(items.into_iter().map(simplify).collect())),
+        e => e,
+    }
+}
+
+// `expr` with `2x` and `2x**2` written as plain products and powers of
+// the variable, so it can be evaluated with `x` set as a constant
+pub fn with_variable(expr: &Expr, var: &str) -> Expr {
+    substitute(expr, var, &Expr::Atom(Atom::Const(var.to_string())))
+}
+
+// a number written next to the variable reads as a unit, so `2x` and
+// `x**2` need turning into plain products and powers first
+fn bind(expr: &Expr, var: &str) -> Expr {
+    match expr {
+        Expr::Atom(Atom::Unit(name)) if name.eq_ignore_ascii_case(var) => Expr::Atom(Atom::Const(name.clone())),
+        Expr::Atom(Atom::Quantity(value, name)) if name.eq_ignore_ascii_case(var) => {
+            mul(Expr::Atom((**value).clone()), Expr::Atom(Atom::Const(name.clone())))
+        },
+        e => e.clone(),
+    }
+}
+
+fn derive(expr: &Expr, var: &str) -> Result<Expr, EvalErr> {
+    if !depends_on(expr, var) {
+        return Result::Ok(int(0));
+    }
+    match expr {
+        Expr::Atom(Atom::Const(_)) => Result::Ok(int(1)),
+        Expr::Atom(Atom::Func(name, args)) => derive_call(name, args, var),
+        Expr::Atom(Atom::Vector(items)) => {
+            let items = items.iter().map(|e| derive(e, var)).collect::<Result<Vec<_>, _>>()?;
+            Result::Ok(Expr::Atom(Atom::Vector(items)))
+        },
+        Expr::Atom(_) => Result::Ok(int(0)),
+        Expr::Index(target, index) => Result::Ok(Expr::Index(Box::new(derive(target, var)?), index.clone())),
+        Expr::UnOp(UnOp::Pos, e) => derive(e, var),
+        Expr::UnOp(UnOp::Neg, e) => Result::Ok(neg(derive(e, var)?)),
+        Expr::UnOp(UnOp::Not, _) => not_differentiable(expr),
+        Expr::BinOp(op, lhs, rhs) => derive_binop(expr, op, lhs, rhs, var),
+    }
+}
+
+fn derive_binop(expr: &Expr, op: &BinOp, f: &Expr, g: &Expr, var: &str) -> Result<Expr, EvalErr> {
+    let (df, dg) = (derive(f, var)?, derive(g, var)?);
+    let (f, g) = (f.clone(), g.clone());
+    let derivative = match op {
+        BinOp::Plus => add(df, dg),
+        BinOp::Minus => sub(df, dg),
+        BinOp::PlusMinus => Expr::BinOp(BinOp::PlusMinus, Box::new(df), Box::new(dg)),
+        BinOp::Mul => add(mul(df, g), mul(f, dg)),
+        BinOp::Div if !depends_on(&g, var) => div(df, g),
+        BinOp::Div => div(sub(mul(df, g.clone()), mul(f, dg)), pow(g, int(2))),
+        // `f % g` steps down by `g` wherever it wraps, so between the
+        // steps only `f` moves it
+        BinOp::Mod if !depends_on(&g, var) => df,
+        BinOp::Pow if !depends_on(&g, var) => mul(mul(g.clone(), pow(f, sub(g, int(1)))), df),
+        BinOp::Pow if !depends_on(&f, var) => mul(mul(pow(f.clone(), g), ln(f)), dg),
+        BinOp::Pow => {
+            let rate = add(mul(dg, ln(f.clone())), div(mul(g.clone(), df), f.clone()));
+            mul(pow(f, g), rate)
+        },
+        BinOp::Shl if !depends_on(&g, var) => mul(df, pow(int(2), g)),
+        BinOp::Shr if !depends_on(&g, var) => div(df, pow(int(2), g)),
+        BinOp::Mod | BinOp::Shl | BinOp::Shr | BinOp::And | BinOp::Or | BinOp::Xor | BinOp::Eq | BinOp::Ne
+        | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge | BinOp::AndAnd | BinOp::OrOr | BinOp::To
+        | BinOp::Equation => {
+            return not_differentiable(expr)
+        },
+    };
+    Result::Ok(derivative)
+}
+
+// the chain rule for the builtin functions
+fn derive_call(name: &str, args: &[Expr], var: &str) -> Result<Expr, EvalErr> {
+    let expr = Expr::Atom(Atom::Func(name.to_string(), args.to_vec()));
+    let name = name.to_lowercase();
+    let arity = match name.as_str() {
+        "sin" | "cos" | "tan" | "exp" | "sqrt" | "abs" | "norm" | "ceil" | "floor" | "deg" | "rad" | "re" | "im"
+        | "conj" | "transpose" | "trace" | "det" | "inv" => 1,
+        "min" | "max" | "avg" | "sum" => args.len().max(1),
+        "solve" if args.len() != 2 => return not_differentiable(&expr),
+        "log" | "dot" | "cross" | "solve" => 2,
+        "if" => 3,
+        "diff" => args.len().clamp(2, 3),
+        "integrate" => args.len().clamp(4, 5),
+        "arg" | "fact" | "ncr" | "npr" | "iv" | "frac" | "convergents" | "len" | "rank" | "identity" | "rotl"
+        | "rotr" | "popcount" | "clz" | "ctz" | "bit" | "roots" | "polyval" | "polyfit" | "polymul" | "median"
+        | "mode" | "var" | "pvar" | "stddev" | "pstddev" | "percentile" | "quantile" | "geomean" | "harmean"
+        | "prod" | "range" | "skew" | "kurtosis" => {
+            return not_differentiable(&expr)
+        },
+        _ => return Result::Err(EvalErr::FuncNotExists(name)),
+    };
+    if args.len() != arity {
+        let msg = format!("expected {}, got {} in {}", arity, args.len(), expr);
+        return Result::Err(EvalErr::IncorrectArgumentCount(msg));
+    }
+
+    // the first argument is a condition or a log base for some functions,
+    // so it is only differentiated where it is needed
+    let u = args[0].clone();
+    let du = || derive(&args[0], var);
+    let derivative = match name.as_str() {
+        "sin" => mul(call("cos", vec![u]), du()?),
+        "cos" => neg(mul(call("sin", vec![u]), du()?)),
+        "tan" => div(du()?, pow(call("cos", vec![u]), int(2))),
+        "exp" => mul(call("exp", vec![u]), du()?),
+        "sqrt" => div(du()?, mul(int(2), call("sqrt", vec![u]))),
+        "abs" => div(mul(u.clone(), du()?), call("abs", vec![u])),
+        "norm" => div(call("dot", vec![u.clone(), du()?]), call("norm", vec![u])),
+        "ceil" | "floor" => int(0),
+        "deg" | "rad" | "re" | "im" | "conj" | "transpose" | "trace" => call(&name, vec![du()?]),
+        "avg" | "sum" => call(&name, args.iter().map(|e| derive(e, var)).collect::<Result<Vec<_>, _>>()?),
+        "min" | "max" if args.len() == 1 => du()?,
+        "min" | "max" => {
+            let (rest, last) = match args {
+                [first, last] => (first.clone(), last.clone()),
+                [rest @ .., last] => (call(&name, rest.to_vec()), last.clone()),
+                [] => unreachable!(),
+            };
+            let cmp = if name == "min" { BinOp::Le } else { BinOp::Ge };
+            let choice = Expr::BinOp(cmp, Box::new(rest.clone()), Box::new(last.clone()));
+            call("if", vec![choice, derive(&rest, var)?, derive(&last, var)?])
+        },
+        "log" => {
+            let (base, x) = (u, args[1].clone());
+            let dx = derive(&x, var)?;
+            match base {
+                Expr::Atom(Atom::Const(ref b)) if b.eq_ignore_ascii_case("e") => div(dx, x),
+                _ if !depends_on(&base, var) => div(dx, mul(x, ln(base))),
+                _ => derive(&div(ln(x), ln(base)), var)?,
+            }
+        },
+        "dot" | "cross" => {
+            let v = args[1].clone();
+            let dv = derive(&v, var)?;
+            add(call(&name, vec![du()?, v.clone()]), call(&name, vec![u, dv]))
+        },
+        "det" => mul(call("det", vec![u.clone()]), call("trace", vec![mul(call("inv", vec![u]), du()?)])),
+        "inv" => neg(mul(mul(call("inv", vec![u.clone()]), du()?), call("inv", vec![u]))),
+        "solve" => {
+            let b = args[1].clone();
+            let db = derive(&b, var)?;
+            let x = call("solve", vec![u.clone(), b]);
+            call("solve", vec![u, sub(db, mul(du()?, x))])
+        },
+        "if" => call("if", vec![u, derive(&args[1], var)?, derive(&args[2], var)?]),
+        // the fundamental theorem of calculus, for bounds that depend on
+        // the variable
+        "integrate" => {
+            let inner_var = variable_name(&args[1])?;
+            if inner_var != var.to_lowercase() && depends_on(&u, var) {
+                return not_differentiable(&expr);
+            }
+            let (a, b) = (&args[2], &args[3]);
+            let upper = mul(substitute(&u, &inner_var, b), derive(b, var)?);
+            let lower = mul(substitute(&u, &inner_var, a), derive(a, var)?);
+            sub(upper, lower)
+        },
+        "diff" => {
+            let inner_var = variable_name(&args[1])?;
+            let inner = derivative(&u, &inner_var)?;
+            let inner = match args.get(2) {
+                Option::Some(point) => substitute(&inner, &inner_var, point),
+                Option::None => inner,
+            };
+            derive(&with_variable(&inner, var), var)?
+        },
+        _ => unreachable!(),
+    };
+    Result::Ok(derivative)
+}
+
+// the name in the variable position of `diff(f, x)` and friends
+pub fn variable(ctx: &Context, expr: &Expr) -> Result<String, EvalErr> {
+    let name = variable_name(expr)?;
+    // `diff(x, pi)` would quietly treat pi as a variable
+    if ctx.consts.contains_key(&name) {
+        return Result::Err(EvalErr::InvalidArgument(format!("{} is a constant, not a variable", expr)));
+    }
+    Result::Ok(name)
+}
+
+// nested calls are checked against the constants when they are evaluated
+fn variable_name(expr: &Expr) -> Result<String, EvalErr> {
+    match expr {
+        Expr::Atom(Atom::Const(name)) | Expr::Atom(Atom::Unit(name)) => Result::Ok(name.to_lowercase()),
+        e => Result::Err(EvalErr::InvalidArgument(format!("expected a variable name, got {}", e))),
+    }
+}
+
+fn not_differentiable(expr: &Expr) -> Result<Expr, EvalErr> {
+    Result::Err(EvalErr::InvalidArgument(format!("cannot differentiate {}", expr)))
+}
+
+fn int(n: i64) -> Expr {
+    integer(Integer::from(n))
+}
+
+// negative constants are kept as a negation, as the parser writes them
+fn integer(n: Integer) -> Expr {
+    if n.is_negative() {
+        return Expr::UnOp(UnOp::Neg, Box::new(Expr::Atom(Atom::Int(-n))));
+    }
+    Expr::Atom(Atom::Int(n))
+}
+
+// a fraction is written as a quotient of integers
+fn rational(r: Rational) -> Expr {
+    if r.is_integer() {
+        return integer(r.to_integer());
+    }
+    let (numer, denom) = (Expr::Atom(Atom::Int(r.numer().abs())), Expr::Atom(Atom::Int(r.denom().clone())));
+    let q = Expr::BinOp(BinOp::Div, Box::new(numer), Box::new(denom));
+    if r.is_negative() {
+        return Expr::UnOp(UnOp::Neg, Box::new(q));
+    }
+    q
+}
+
+fn constant(expr: &Expr) -> Option<Rational> {
+    match expr {
+        Expr::Atom(Atom::Int(n)) => Option::Some(Rational::from_integer(n.clone())),
+        Expr::UnOp(UnOp::Neg, e) => constant(e).map(|r| -r),
+        Expr::BinOp(BinOp::Div, a, b) => match (constant(a)?, constant(b)?) {
+            (x, y) if !y.is_zero() => Option::Some(x / y),
+            _ => Option::None,
+        },
+        _ => Option::None,
+    }
+}
+
+fn is(expr: &Expr, n: i64) -> bool {
+    constant(expr) == Option::Some(Rational::from_integer(Integer::from(n)))
+}
+
+fn call(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Atom(Atom::Func(name.to_string(), args))
+}
+
+fn ln(x: Expr) -> Expr {
+    if let Expr::Atom(Atom::Const(name)) = &x {
+        if name.eq_ignore_ascii_case("e") {
+            return int(1);
+        }
+    }
+    call("log", vec![Expr::Atom(Atom::Const("e".to_string())), x])
+}
+
+fn neg(a: Expr) -> Expr {
+    match a {
+        Expr::UnOp(UnOp::Neg, e) => *e,
+        a if is(&a, 0) => a,
+        Expr::BinOp(BinOp::Mul, c, rest) if constant(&c).is_some() => mul(neg(*c), *rest),
+        a => Expr::UnOp(UnOp::Neg, Box::new(a)),
+    }
+}
+
+fn add(a: Expr, b: Expr) -> Expr {
+    if let (Option::Some(x), Option::Some(y)) = (constant(&a), constant(&b)) {
+        return rational(x + y);
+    }
+    match (a, b) {
+        (a, b) if is(&a, 0) => b,
+        (a, b) if is(&b, 0) => a,
+        (a, Expr::UnOp(UnOp::Neg, b)) => sub(a, *b),
+        (Expr::UnOp(UnOp::Neg, a), b) => sub(b, *a),
+        (a, b) if a == b => mul(int(2), a),
+        (a, b) => Expr::BinOp(BinOp::Plus, Box::new(a), Box::new(b)),
+    }
+}
+
+fn sub(a: Expr, b: Expr) -> Expr {
+    if let (Option::Some(x), Option::Some(y)) = (constant(&a), constant(&b)) {
+        return rational(x - y);
+    }
+    match (a, b) {
+        (a, b) if is(&b, 0) => a,
+        (a, b) if is(&a, 0) => neg(b),
+        (a, b) if a == b => int(0),
+        (a, Expr::UnOp(UnOp::Neg, b)) => add(a, *b),
+        (a, b) => Expr::BinOp(BinOp::Minus, Box::new(a), Box::new(b)),
+    }
+}
+
+fn mul(a: Expr, b: Expr) -> Expr {
+    if let (Option::Some(x), Option::Some(y)) = (constant(&a), constant(&b)) {
+        return rational(x * y);
+    }
+    match (a, b) {
+        (a, b) if is(&a, 0) || is(&b, 0) => int(0),
+        (a, b) if is(&a, 1) => b,
+        (a, b) if is(&b, 1) => a,
+        (a, b) if is(&a, -1) => neg(b),
+        // a negative coefficient stays in front, so `-2 * x` reads as written
+        (Expr::UnOp(UnOp::Neg, a), b) if constant(&a).is_none() => neg(mul(*a, b)),
+        (a, Expr::UnOp(UnOp::Neg, b)) if constant(&b).is_none() => neg(mul(a, *b)),
+        // constants gather at the front, as in `6*x` for `2*(3*x)`
+        (a, b) if constant(&b).is_some() => mul(b, a),
+        (a, Expr::BinOp(BinOp::Mul, c, rest)) if constant(&a).is_some() && constant(&c).is_some() => {
+            mul(mul(a, *c), *rest)
+        },
+        (a, b) if a == b => pow(a, int(2)),
+        (a, b) => Expr::BinOp(BinOp::Mul, Box::new(a), Box::new(b)),
+    }
+}
+
+fn div(a: Expr, b: Expr) -> Expr {
+    if let (Option::Some(x), Option::Some(y)) = (constant(&a), constant(&b)) {
+        if !y.is_zero() {
+            return rational(x / y);
+        }
+    }
+    match (a, b) {
+        (a, b) if is(&b, 1) => a,
+        // a constant coefficient cancels, as in `x` for `2 * x / 2`
+        (Expr::BinOp(BinOp::Mul, c, rest), b) if constant(&c).is_some() && constant(&b).is_some_and(|y| !y.is_zero()) => {
+            mul(div(*c, b), *rest)
+        },
+        (a, b) if is(&a, 0) && !is(&b, 0) => a,
+        (a, b) if a == b && !is(&b, 0) => int(1),
+        (a, Expr::UnOp(UnOp::Neg, b)) => div(neg(a), *b),
+        (a, b) => Expr::BinOp(BinOp::Div, Box::new(a), Box::new(b)),
+    }
+}
+
+fn pow(a: Expr, b: Expr) -> Expr {
+    if let (Option::Some(x), Option::Some(n)) = (constant(&a), constant(&b)) {
+        let bits = x.numer().bits().max(x.denom().bits());
+        let folded = n.to_integer().to_i32().filter(|&k| n.is_integer() && k >= 0);
+        if let Option::Some(n) = folded.filter(|&n| bits.saturating_mul(n as u64) <= MAX_FOLDED_BITS) {
+            return rational(x.pow(n));
+        }
+    }
+    match (a, b) {
+        (_, b) if is(&b, 0) => int(1),
+        (a, b) if is(&b, 1) || is(&a, 1) => a,
+        (a, b) => Expr::BinOp(BinOp::Pow, Box::new(a), Box::new(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::constants::builtin_consts;
+    use crate::calc::lexer::Lexer;
+    use crate::calc::parser::Parser;
+    use crate::calc::value::Value;
+
+    fn d(expr: &str) -> String {
+        let expr = Parser::new(Lexer::new(expr.to_string())).parse().unwrap();
+        derivative(&expr, "x").unwrap().to_string()
+    }
+
+    #[test]
+    fn derivative_rules() {
+        assert_eq!(d("x**3 + 2x"), "3 * x**2 + 2");
+        assert_eq!(d("sin(x)*x"), "cos(x) * x + sin(x)");
+        assert_eq!(d("1/x"), "-1 / x**2");
+        assert_eq!(d("2**x"), "2**x * log(e, 2)");
+        assert_eq!(d("log(e, x**2)"), "2 * x / x**2");
+        assert_eq!(d("sqrt(5)*y"), "0");
+        // constant coefficients cancel and fractions fold
+        assert_eq!(d("x**2/2"), "x");
+        assert_eq!(d("3*x**2/4"), "3 / 2 * x");
+        assert_eq!(d("x**(1/2)"), "1 / 2 * x**-(1 / 2)");
+        assert_eq!(d("x**(3/2) - x/3"), "3 / 2 * x**(1 / 2) - 1 / 3");
+    }
+
+    #[test]
+    fn constants_are_not_variables() {
+        let name = |s: &str| Parser::new(Lexer::new(s.to_string())).parse().unwrap();
+        let mut ctx = Context { consts: builtin_consts(Option::None), ..Context::default() };
+        assert!(variable(&ctx, &name("pi")).is_err());
+        assert!(variable(&ctx, &name("E")).is_err());
+        assert_eq!(variable(&ctx, &name("x")), Result::Ok("x".to_string()));
+        // as with --ignore-consts and --extra-consts
+        ctx.consts.remove("e");
+        ctx.consts.insert("k".to_string(), Value::Float(1.38e-23));
+        assert_eq!(variable(&ctx, &name("e")), Result::Ok("e".to_string()));
+        assert!(variable(&ctx, &name("k")).is_err());
+    }
+
+    #[test]
+    fn simplification() {
+        let expr = Parser::new(Lexer::new("0*y + 1*(z - 0) + 2*(3*w)".to_string())).parse().unwrap();
+        assert_eq!(simplify(expr).to_string(), "z + 6 * w");
+    }
+}
@@ -11,12 +11,27 @@ pub enum Token {
     Div(usize),
     Mod(usize),
     Pow(usize),
+    PlusMinus(usize),
     And(usize),
     Or(usize),
     Not(usize),
     Xor(usize),
+    Eq(usize),
+    Assign(usize),
+    Ne(usize),
+    Lt(usize),
+    Le(usize),
+    Gt(usize),
+    Ge(usize),
+    AndAnd(usize),
+    OrOr(usize),
+    Shl(usize),
+    Shr(usize),
+    To(usize),
     Lparen(usize),
     Rparen(usize),
+    Lbracket(usize),
+    Rbracket(usize),
     Comma(usize),
     Eof(usize),
 }
@@ -33,12 +48,27 @@ impl fmt::Debug for Token {
             Token::Div(_) => write!(f, "Token::DIV"),
             Token::Mod(_) => write!(f, "Token::MOD"),
             Token::Pow(_) => write!(f, "Token::POW"),
+            Token::PlusMinus(_) => write!(f, "Token::PLUSMINUS"),
             Token::And(_) => write!(f, "Token::AND"),
             Token::Or(_) => write!(f, "Token::OR"),
             Token::Not(_) => write!(f, "Token::NOT"),
             Token::Xor(_) => write!(f, "Token::XOR"),
+            Token::Eq(_) => write!(f, "Token::EQ"),
+            Token::Assign(_) => write!(f, "Token::ASSIGN"),
+            Token::Ne(_) => write!(f, "Token::NE"),
+            Token::Lt(_) => write!(f, "Token::LT"),
+            Token::Le(_) => write!(f, "Token::LE"),
+            Token::Gt(_) => write!(f, "Token::GT"),
+            Token::Ge(_) => write!(f, "Token::GE"),
+            Token::AndAnd(_) => write!(f, "Token::ANDAND"),
+            Token::OrOr(_) => write!(f, "Token::OROR"),
+            Token::Shl(_) => write!(f, "Token::SHL"),
+            Token::Shr(_) => write!(f, "Token::SHR"),
+            Token::To(_) => write!(f, "Token::TO"),
             Token::Lparen(_) => write!(f, "Token::LPAREN"),
             Token::Rparen(_) => write!(f, "Token::RPAREN"),
+            Token::Lbracket(_) => write!(f, "Token::LBRACKET"),
+            Token::Rbracket(_) => write!(f, "Token::RBRACKET"),
             Token::Comma(_) => write!(f, "Token::COMMA"),
             Token::Eof(_) => write!(f, "Token::EOF"),
         }
@@ -0,0 +1,158 @@
+use crate::calc::common::Float;
+use std::fmt;
+
+// a value with a standard deviation, propagated to first order assuming the
+// operands of every operation are independent
+#[derive(Clone, Copy, PartialEq)]
+pub struct Uncertain {
+    value: Float,
+    sigma: Float,
+}
+
+impl Uncertain {
+    pub fn exact(value: Float) -> Uncertain {
+        Uncertain { value, sigma: 0.0 }
+    }
+
+    pub fn value(&self) -> Float {
+        self.value
+    }
+
+    pub fn sigma(&self) -> Float {
+        self.sigma
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.value.is_finite() && self.sigma.is_finite()
+    }
+
+    // applies a function of one variable given its derivative at the value
+    pub fn map(&self, f: fn(Float) -> Float, df: fn(Float) -> Float) -> Uncertain {
+        Uncertain {
+            value: f(self.value),
+            sigma: spread(df(self.value), self.sigma),
+        }
+    }
+
+    pub fn scale(&self, k: Float) -> Uncertain {
+        Uncertain { value: self.value * k, sigma: spread(k, self.sigma) }
+    }
+
+    pub fn neg(&self) -> Uncertain {
+        Uncertain { value: -self.value, sigma: self.sigma }
+    }
+
+    pub fn add(&self, rhs: &Uncertain) -> Uncertain {
+        self.combine(rhs, self.value + rhs.value, 1.0, 1.0)
+    }
+
+    pub fn sub(&self, rhs: &Uncertain) -> Uncertain {
+        self.combine(rhs, self.value - rhs.value, 1.0, -1.0)
+    }
+
+    pub fn mul(&self, rhs: &Uncertain) -> Uncertain {
+        self.combine(rhs, self.value * rhs.value, rhs.value, self.value)
+    }
+
+    pub fn div(&self, rhs: &Uncertain) -> Option<Uncertain> {
+        if rhs.value == 0.0 {
+            return Option::None;
+        }
+        let q = self.value / rhs.value;
+        Option::Some(self.combine(rhs, q, 1.0 / rhs.value, -q / rhs.value))
+    }
+
+    pub fn rem(&self, rhs: &Uncertain) -> Option<Uncertain> {
+        if rhs.value == 0.0 {
+            return Option::None;
+        }
+        let turns = (self.value / rhs.value).trunc();
+        Option::Some(self.combine(rhs, self.value % rhs.value, 1.0, -turns))
+    }
+
+    // None for a negative base with a fractional or uncertain exponent, and
+    // for zero to a negative power
+    pub fn pow(&self, rhs: &Uncertain) -> Option<Uncertain> {
+        let p = self.value.powf(rhs.value);
+        if p.is_nan() || (self.value == 0.0 && rhs.value < 0.0) || (self.value < 0.0 && rhs.sigma != 0.0) {
+            return Option::None;
+        }
+        let d_base = rhs.value * self.value.powf(rhs.value - 1.0);
+        // the exponent only contributes when it is itself uncertain, which
+        // keeps negative bases with exact exponents well defined
+        let d_exp = if rhs.sigma == 0.0 { 0.0 } else { p * self.value.ln() };
+        Option::Some(self.combine(rhs, p, d_base, d_exp))
+    }
+
+    pub fn hypot(&self, sigma: Float) -> Uncertain {
+        Uncertain { value: self.value, sigma: self.sigma.hypot(sigma) }
+    }
+
+    fn combine(&self, rhs: &Uncertain, value: Float, d_lhs: Float, d_rhs: Float) -> Uncertain {
+        let sigma = spread(d_lhs, self.sigma).hypot(spread(d_rhs, rhs.sigma));
+        Uncertain { value, sigma }
+    }
+}
+
+// an exact operand contributes nothing, even where the derivative blows up
+fn spread(derivative: Float, sigma: Float) -> Float {
+    if sigma == 0.0 {
+        return 0.0;
+    }
+    (derivative * sigma).abs()
+}
+
+impl fmt::Display for Uncertain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sigma == 0.0 || !self.is_finite() {
+            return write!(f, "{} ± {}", self.value, self.sigma);
+        }
+        // quote the uncertainty to two significant figures and the value to
+        // the same decimal place
+        let decimals = (1 - self.sigma.log10().floor() as i32).max(0) as usize;
+        write!(f, "{:.*} ± {:.*}", decimals, self.value, decimals, self.sigma)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(value: Float, sigma: Float) -> Uncertain {
+        Uncertain { value, sigma }
+    }
+
+    fn close(a: Float, b: Float) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn first_order_propagation() {
+        let sum = u(1.0, 0.3).add(&u(2.0, 0.4));
+        assert!(close(sum.value(), 3.0) && close(sum.sigma(), 0.5));
+        let product = u(2.0, 0.1).mul(&u(3.0, 0.2));
+        assert!(close(product.value(), 6.0) && close(product.sigma(), 0.5));
+        let quotient = u(6.0, 0.3).div(&u(2.0, 0.0)).unwrap();
+        assert!(close(quotient.value(), 3.0) && close(quotient.sigma(), 0.15));
+        // an exact exponent lets a negative base through
+        let cube = u(-2.0, 0.1).pow(&Uncertain::exact(3.0)).unwrap();
+        assert!(close(cube.value(), -8.0) && close(cube.sigma(), 1.2));
+    }
+
+    #[test]
+    fn undefined_results() {
+        assert!(u(1.0, 0.1).div(&u(0.0, 0.1)).is_none());
+        assert!(u(-2.0, 0.1).pow(&Uncertain::exact(0.5)).is_none());
+        assert!(u(0.0, 0.1).pow(&Uncertain::exact(-1.0)).is_none());
+        assert!(Uncertain::exact(-2.0).pow(&u(3.0, 0.1)).is_none());
+        assert!(u(1.0, 0.1).rem(&Uncertain::exact(0.0)).is_none());
+        let rest = u(7.0, 0.1).rem(&u(2.0, 0.1)).unwrap();
+        assert!(close(rest.value(), 1.0) && close(rest.sigma(), 0.1 * 10f64.sqrt()));
+    }
+
+    #[test]
+    fn display_rounds_to_the_uncertainty() {
+        assert_eq!(u(1.23456, 0.0123).to_string(), "1.235 ± 0.012");
+        assert_eq!(Uncertain::exact(2.5).to_string(), "2.5 ± 0");
+    }
+}
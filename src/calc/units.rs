@@ -0,0 +1,256 @@
+use crate::calc::common::{Integer, Rational};
+use crate::calc::eval::EvalErr;
+use bigdecimal::BigDecimal;
+use num_traits::Zero;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// exponents of the SI base units, in display order
+const BASE_UNITS: [&str; 7] = ["kg", "m", "s", "A", "K", "mol", "cd"];
+
+const PREFIXES: [(&str, i32); 20] = [
+    ("Q", 30), ("R", 27), ("Y", 24), ("Z", 21), ("E", 18),
+    ("P", 15), ("T", 12), ("G", 9), ("M", 6), ("k", 3),
+    ("h", 2), ("da", 1), ("d", -1), ("c", -2), ("m", -3),
+    ("u", -6), ("n", -9), ("p", -12), ("f", -15), ("a", -18),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Dimension([i32; 7]);
+
+impl Dimension {
+    pub const NONE: Dimension = Dimension([0; 7]);
+
+    pub fn new(kg: i32, m: i32, s: i32, a: i32, k: i32, mol: i32, cd: i32) -> Dimension {
+        Dimension([kg, m, s, a, k, mol, cd])
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Dimension::NONE
+    }
+
+    pub fn mul(&self, rhs: &Dimension) -> Result<Dimension, EvalErr> {
+        self.combine(|i| self.0[i].checked_add(rhs.0[i]))
+    }
+
+    pub fn div(&self, rhs: &Dimension) -> Result<Dimension, EvalErr> {
+        self.combine(|i| self.0[i].checked_sub(rhs.0[i]))
+    }
+
+    pub fn powi(&self, n: i32) -> Result<Dimension, EvalErr> {
+        self.combine(|i| self.0[i].checked_mul(n))
+    }
+
+    // the n-th root, if every exponent is divisible by n
+    pub fn root(&self, n: i32) -> Option<Dimension> {
+        if self.0.iter().any(|e| e.checked_rem(n) != Option::Some(0)) {
+            return Option::None;
+        }
+        self.combine(|i| self.0[i].checked_div(n)).ok()
+    }
+
+    fn combine(&self, exponent: impl Fn(usize) -> Option<i32>) -> Result<Dimension, EvalErr> {
+        let mut exponents = [0; 7];
+        for (i, e) in exponents.iter_mut().enumerate() {
+            *e = exponent(i).ok_or_else(|| {
+                EvalErr::Overflow(format!("exponent of {} is out of the 32-bit range", BASE_UNITS[i]))
+            })?;
+        }
+        Result::Ok(Dimension(exponents))
+    }
+}
+
+// a value `x` in this unit is `x * factor + offset` in SI base units; only
+// temperature scales have an offset. Factors are exact so conversions
+// round-trip without picking up binary rounding errors
+#[derive(Clone, PartialEq)]
+pub struct Unit {
+    pub factor: Rational,
+    pub offset: Rational,
+    pub dim: Dimension,
+    pub prefixable: bool,
+}
+
+impl Unit {
+    pub fn si(factor: Rational, dim: Dimension) -> Unit {
+        Unit { factor, offset: Rational::zero(), dim, prefixable: true }
+    }
+
+    pub fn plain(factor: Rational, dim: Dimension) -> Unit {
+        Unit { factor, offset: Rational::zero(), dim, prefixable: false }
+    }
+
+    fn affine(factor: Rational, offset: Rational, dim: Dimension) -> Unit {
+        Unit { factor, offset, dim, prefixable: false }
+    }
+}
+
+// parses a decimal literal such as "0.3048" or "1.6e-19" exactly
+fn exact(s: &str) -> Rational {
+    let (digits, scale) = BigDecimal::from_str(s).unwrap_or_default().into_bigint_and_exponent();
+    let ten = Rational::from_integer(Integer::from(10));
+    Rational::from_integer(digits) / ten.pow(scale as i32)
+}
+
+pub fn builtin_units() -> HashMap<String, Unit> {
+    let mass = Dimension::new(1, 0, 0, 0, 0, 0, 0);
+    let length = Dimension::new(0, 1, 0, 0, 0, 0, 0);
+    let time = Dimension::new(0, 0, 1, 0, 0, 0, 0);
+    let current = Dimension::new(0, 0, 0, 1, 0, 0, 0);
+    let temperature = Dimension::new(0, 0, 0, 0, 1, 0, 0);
+    let amount = Dimension::new(0, 0, 0, 0, 0, 1, 0);
+    let luminosity = Dimension::new(0, 0, 0, 0, 0, 0, 1);
+    let frequency = Dimension::new(0, 0, -1, 0, 0, 0, 0);
+    let speed = Dimension::new(0, 1, -1, 0, 0, 0, 0);
+    let volume = Dimension::new(0, 3, 0, 0, 0, 0, 0);
+
+    let force = Dimension::new(1, 1, -2, 0, 0, 0, 0);
+    let energy = Dimension::new(1, 2, -2, 0, 0, 0, 0);
+    let power = Dimension::new(1, 2, -3, 0, 0, 0, 0);
+    let charge = Dimension::new(0, 0, 1, 1, 0, 0, 0);
+    let voltage = Dimension::new(1, 2, -3, -1, 0, 0, 0);
+    let resistance = Dimension::new(1, 2, -3, -2, 0, 0, 0);
+    let capacitance = Dimension::new(-1, -2, 4, 2, 0, 0, 0);
+    let pressure = Dimension::new(1, -1, -2, 0, 0, 0, 0);
+
+    let pound_force = exact("0.45359237") * exact("9.80665");
+
+    let mut units: HashMap<String, Unit> = HashMap::new();
+    units.insert("g".into(), Unit::si(exact("1e-3"), mass));
+    units.insert("m".into(), Unit::si(exact("1"), length));
+    units.insert("s".into(), Unit::si(exact("1"), time));
+    units.insert("A".into(), Unit::si(exact("1"), current));
+    units.insert("K".into(), Unit::si(exact("1"), temperature));
+    units.insert("mol".into(), Unit::si(exact("1"), amount));
+    units.insert("cd".into(), Unit::si(exact("1"), luminosity));
+
+    units.insert("Hz".into(), Unit::si(exact("1"), frequency));
+    units.insert("N".into(), Unit::si(exact("1"), force));
+    units.insert("Pa".into(), Unit::si(exact("1"), pressure));
+    units.insert("J".into(), Unit::si(exact("1"), energy));
+    units.insert("W".into(), Unit::si(exact("1"), power));
+    units.insert("C".into(), Unit::si(exact("1"), charge));
+    units.insert("V".into(), Unit::si(exact("1"), voltage));
+    units.insert("ohm".into(), Unit::si(exact("1"), resistance));
+    units.insert("F".into(), Unit::si(exact("1"), capacitance));
+    units.insert("L".into(), Unit::si(exact("1e-3"), volume));
+
+    units.insert("min".into(), Unit::plain(exact("60"), time));
+    units.insert("h".into(), Unit::plain(exact("3600"), time));
+    units.insert("day".into(), Unit::plain(exact("86400"), time));
+    units.insert("t".into(), Unit::si(exact("1e3"), mass));
+    units.insert("eV".into(), Unit::si(exact("1.602176634e-19"), energy));
+    units.insert("Wh".into(), Unit::si(exact("3600"), energy));
+    units.insert("cal".into(), Unit::si(exact("4.184"), energy));
+    units.insert("bar".into(), Unit::si(exact("1e5"), pressure));
+    units.insert("atm".into(), Unit::plain(exact("101325"), pressure));
+
+    units.insert("inch".into(), Unit::plain(exact("0.0254"), length));
+    units.insert("ft".into(), Unit::plain(exact("0.3048"), length));
+    units.insert("yd".into(), Unit::plain(exact("0.9144"), length));
+    units.insert("mi".into(), Unit::plain(exact("1609.344"), length));
+    units.insert("nmi".into(), Unit::plain(exact("1852"), length));
+    units.insert("mph".into(), Unit::plain(exact("1609.344") / exact("3600"), speed));
+    units.insert("kn".into(), Unit::plain(exact("1852") / exact("3600"), speed));
+    units.insert("lb".into(), Unit::plain(exact("0.45359237"), mass));
+    units.insert("oz".into(), Unit::plain(exact("0.45359237") / exact("16"), mass));
+    units.insert("gal".into(), Unit::plain(exact("3.785411784e-3"), volume));
+    units.insert("psi".into(), Unit::plain(&pound_force / exact("0.0254").pow(2), pressure));
+    units.insert("hp".into(), Unit::plain(exact("550") * exact("0.3048") * &pound_force, power));
+
+    units.insert("degC".into(), Unit::affine(exact("1"), exact("273.15"), temperature));
+    let rankine = exact("5") / exact("9");
+    units.insert("degF".into(), Unit::affine(rankine.clone(), exact("459.67") * &rankine, temperature));
+    units.insert("degR".into(), Unit::plain(rankine, temperature));
+    units
+}
+
+// looks a unit up by name, falling back to an SI prefix on a prefixable unit
+pub fn find_unit(units: &HashMap<String, Unit>, name: &str) -> Option<Unit> {
+    if let Option::Some(unit) = units.get(name) {
+        return Option::Some(unit.clone());
+    }
+    for (prefix, scale) in PREFIXES {
+        let Option::Some(rest) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        match units.get(rest) {
+            Option::Some(unit) if unit.prefixable => {
+                let scale = Rational::from_integer(Integer::from(10)).pow(scale);
+                return Option::Some(Unit { factor: &unit.factor * scale, ..unit.clone() });
+            },
+            _ => continue,
+        }
+    }
+    Option::None
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let term = |name: &str, e: i64| match e {
+            1 => name.to_string(),
+            e => format!("{}**{}", name, e),
+        };
+        let numer: Vec<String> = (0..7)
+            .filter(|&i| self.0[i] > 0)
+            .map(|i| term(BASE_UNITS[i], self.0[i] as i64))
+            .collect();
+        let denom: Vec<String> = (0..7)
+            .filter(|&i| self.0[i] < 0)
+            .map(|i| term(BASE_UNITS[i], -(self.0[i] as i64)))
+            .collect();
+
+        if numer.is_empty() {
+            let inverse: Vec<String> = (0..7)
+                .filter(|&i| self.0[i] < 0)
+                .map(|i| term(BASE_UNITS[i], self.0[i] as i64))
+                .collect();
+            return write!(f, "{}", inverse.join("*"));
+        }
+        write!(f, "{}", numer.join("*"))?;
+        match denom.len() {
+            0 => Result::Ok(()),
+            1 => write!(f, "/{}", denom[0]),
+            _ => write!(f, "/({})", denom.join("*")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::One;
+
+    #[test]
+    fn prefixed_units() {
+        let units = builtin_units();
+        let km = find_unit(&units, "km").unwrap();
+        assert_eq!(km.factor, Rational::from_integer(Integer::from(1000)));
+        assert_eq!(km.dim.to_string(), "m");
+        assert_eq!(find_unit(&units, "kg").unwrap().factor, Rational::one());
+        assert!(find_unit(&units, "kmin").is_none());
+    }
+
+    #[test]
+    fn dimension_display() {
+        let force = Dimension::new(1, 1, -2, 0, 0, 0, 0);
+        assert_eq!(force.to_string(), "kg*m/s**2");
+        assert_eq!(force.div(&Dimension::new(0, 2, 0, 0, 0, 0, 0)).unwrap().to_string(), "kg/(m*s**2)");
+        assert_eq!(Dimension::new(0, 0, -1, 0, 0, 0, 0).to_string(), "s**-1");
+        assert_eq!(Dimension::new(0, i32::MIN, 0, 0, 0, 0, 0).to_string(), "m**-2147483648");
+    }
+
+    #[test]
+    fn exponent_overflow() {
+        let length = Dimension::new(0, 1, 0, 0, 0, 0, 0);
+        let huge = length.powi(65536).unwrap();
+        assert!(matches!(huge.powi(65536), Result::Err(EvalErr::Overflow(_))));
+        let max = length.powi(i32::MAX).unwrap();
+        assert!(matches!(max.mul(&length), Result::Err(EvalErr::Overflow(_))));
+        assert!(matches!(max.powi(-1).unwrap().div(&length.powi(2).unwrap()), Result::Err(EvalErr::Overflow(_))));
+        let min = max.powi(-1).unwrap().div(&length).unwrap();
+        assert!(min.root(-1).is_none());
+        assert_eq!(min.root(2).unwrap().to_string(), "m**-1073741824");
+    }
+}
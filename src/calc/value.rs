@@ -1,16 +1,54 @@
-use crate::calc::common::{Float, Integer};
+use crate::calc::bigfloat::BigFloat;
+use crate::calc::common::{Complex, Float, Integer, Rational};
 use crate::calc::context::Context;
 use crate::calc::eval::EvalErr;
+use crate::calc::format::Format;
+use crate::calc::fraction;
+use crate::calc::interval::Interval;
+use crate::calc::matrix::Matrix;
+use crate::calc::nodes::Expr;
+use crate::calc::uncertain::Uncertain;
+use crate::calc::units::Dimension;
+use bigdecimal::RoundingMode;
+use num_integer::Integer as _;
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
 use std::fmt;
 
+// largest integer magnitude kept exact before reporting an overflow
+pub const MAX_INTEGER_BITS: u64 = 1 << 22;
+
 #[derive(Clone, PartialEq)]
 pub enum Value {
+    Bool(bool),
     Int(Integer),
     Float(Float),
+    Rational(Rational),
+    Complex(Complex),
+    BigFloat(BigFloat),
+    Interval(Interval),
+    Uncertain(Uncertain),
+    Quantity(Box<Value>, Dimension),
+    // the result of `to`: the value in the target unit, the unit as written
+    // and the same value in SI base units
+    Converted(Box<Value>, String, Box<Value>),
+    // the result of `to hex` and friends: an integer shown in another base
+    Radix(Box<Value>, u32),
+    Vector(Vec<Value>),
+    Matrix(Matrix),
+    // an unevaluated expression, such as the derivative from `diff`
+    Symbolic(Box<Expr>),
 }
 
 impl Value {
     pub fn from_string(s: String) -> Option<Value> {
+        if s.contains('/') {
+            return match s.parse::<Rational>() {
+                Result::Ok(v) => Option::Some(Value::from_ratio(v)),
+                Result::Err(_) => Option::None,
+            };
+        }
+
         if s.contains(".") {
             return match s.parse::<Float>() {
                 Result::Ok(v) => Option::Some(Value::Float(v)),
@@ -26,79 +64,915 @@ impl Value {
 
     pub fn auto(f: Float) -> Value {
         if f.fract() == 0.0 {
-            return Value::Int(f as Integer);
+            if let Option::Some(i) = Integer::from_f64(f) {
+                return Value::Int(i);
+            }
         }
         Value::Float(f)
     }
 
+    pub fn from_ratio(r: Rational) -> Value {
+        if r.is_integer() {
+            return Value::Int(r.to_integer());
+        }
+        Value::Rational(r)
+    }
+
+    pub fn from_complex(c: Complex) -> Value {
+        if c.im == 0.0 {
+            return Value::Float(c.re);
+        }
+        Value::Complex(c)
+    }
+
     pub fn zero() -> Value {
-        Value::Int(0)
+        Value::Int(Integer::zero())
     }
 
     pub fn as_int(&self) -> Integer {
         match self {
-            Value::Int(i) => *i,
-            Value::Float(f) => *f as Integer,
+            Value::Bool(b) => Integer::from(*b as u8),
+            Value::Int(i) => i.clone(),
+            Value::Float(f) => Integer::from_f64(f.trunc()).unwrap_or_default(),
+            Value::Rational(r) => r.to_integer(),
+            Value::Complex(c) => Integer::from_f64(c.re.trunc()).unwrap_or_default(),
+            Value::BigFloat(b) => b.trunc(),
+            Value::Interval(iv) => Integer::from_f64(((iv.lo() + iv.hi()) / 2.0).trunc()).unwrap_or_default(),
+            Value::Uncertain(u) => Integer::from_f64(u.value().trunc()).unwrap_or_default(),
+            Value::Quantity(v, _) => v.as_int(),
+            Value::Converted(_, _, si) => si.as_int(),
+            Value::Radix(v, _) => v.as_int(),
+            Value::Vector(_) | Value::Matrix(_) | Value::Symbolic(_) => Integer::zero(),
         }
     }
 
     pub fn as_float(&self) -> Float {
         match self {
-            Value::Int(i) => *i as Float,
+            Value::Bool(_) => Float::NAN,
+            Value::Int(i) => i.to_f64().unwrap_or(Float::NAN),
             Value::Float(f) => *f,
+            Value::Rational(r) => r.to_f64().unwrap_or(Float::NAN),
+            Value::Complex(c) if c.im == 0.0 => c.re,
+            Value::Complex(_) => Float::NAN,
+            Value::BigFloat(b) => b.to_float(),
+            Value::Interval(iv) if iv.lo() == iv.hi() => iv.lo(),
+            Value::Interval(_) => Float::NAN,
+            Value::Uncertain(u) => u.value(),
+            Value::Quantity(v, _) => v.as_float(),
+            Value::Converted(_, _, si) => si.as_float(),
+            Value::Radix(v, _) => v.as_float(),
+            Value::Vector(_) | Value::Matrix(_) | Value::Symbolic(_) => Float::NAN,
         }
     }
 
-    pub fn add(self, rhs: Value) -> Value {
-        match (self, rhs) {
-            (Value::Int(a), Value::Int(b)) => Value::Int(a + b),
-            (a, b) => Value::Float(a.as_float() + b.as_float()),
+    pub fn as_interval(&self) -> Option<Interval> {
+        match self {
+            Value::Int(i) => {
+                let f = i.to_f64().unwrap_or(Float::NAN);
+                match Integer::from_f64(f) {
+                    Option::Some(exact) if &exact == i => Option::Some(Interval::point(f)),
+                    _ => Option::Some(Interval::around(f)),
+                }
+            },
+            Value::Float(f) => Option::Some(Interval::point(*f)),
+            Value::Rational(r) => {
+                let f = r.to_f64().unwrap_or(Float::NAN);
+                match Rational::from_float(f) {
+                    Option::Some(exact) if &exact == r => Option::Some(Interval::point(f)),
+                    _ => Option::Some(Interval::around(f)),
+                }
+            },
+            Value::BigFloat(b) => Option::Some(Interval::around(b.to_float())),
+            Value::Interval(iv) => Option::Some(*iv),
+            Value::Radix(v, _) => v.as_interval(),
+            Value::Bool(_) | Value::Complex(_) | Value::Uncertain(_) | Value::Quantity(..)
+            | Value::Converted(..) | Value::Vector(_) | Value::Matrix(_) | Value::Symbolic(_) => Option::None,
         }
     }
 
-    pub fn sub(self, rhs: Value) -> Value {
-        match (self, rhs) {
-            (Value::Int(a), Value::Int(b)) => Value::Int(a - b),
-            (a, b) => Value::Float(a.as_float() - b.as_float()),
+    pub fn is_interval(&self) -> bool {
+        matches!(self, Value::Interval(_))
+    }
+
+    fn interval_pair(&self, rhs: &Value) -> Result<(Interval, Interval), EvalErr> {
+        match (self.as_interval(), rhs.as_interval()) {
+            (Option::Some(x), Option::Some(y)) => Result::Ok((x, y)),
+            _ => Result::Err(EvalErr::InvalidArgument(format!(
+                "intervals can only be combined with real numbers, got {} and {}", self, rhs
+            ))),
         }
     }
 
-    pub fn mul(self, rhs: Value) -> Value {
-        match (self, rhs) {
-            (Value::Int(a), Value::Int(b)) => Value::Int(a * b),
-            (a, b) => Value::Float(a.as_float() * b.as_float()),
+    pub fn as_uncertain(&self) -> Option<Uncertain> {
+        match self {
+            Value::Uncertain(u) => Option::Some(*u),
+            Value::Bool(_) | Value::Complex(_) | Value::Interval(_) | Value::Quantity(..)
+            | Value::Converted(..) | Value::Vector(_) | Value::Matrix(_) | Value::Symbolic(_) => Option::None,
+            v => Option::Some(Uncertain::exact(v.as_float())),
+        }
+    }
+
+    pub fn is_uncertain(&self) -> bool {
+        matches!(self, Value::Uncertain(_))
+    }
+
+    fn uncertain_pair(&self, rhs: &Value) -> Result<(Uncertain, Uncertain), EvalErr> {
+        match (self.as_uncertain(), rhs.as_uncertain()) {
+            (Option::Some(x), Option::Some(y)) => Result::Ok((x, y)),
+            _ => Result::Err(EvalErr::InvalidArgument(format!(
+                "uncertain values can only be combined with real numbers, got {} and {}", self, rhs
+            ))),
+        }
+    }
+
+    pub fn quantity(v: Value, dim: Dimension) -> Value {
+        if dim.is_none() {
+            return v;
+        }
+        Value::Quantity(Box::new(v), dim)
+    }
+
+    pub fn is_quantity(&self) -> bool {
+        matches!(self, Value::Quantity(..))
+    }
+
+    pub fn dim(&self) -> Dimension {
+        match self {
+            Value::Quantity(_, dim) => *dim,
+            _ => Dimension::NONE,
+        }
+    }
+
+    // drops the display unit of a converted value
+    pub fn into_si(self) -> Value {
+        match self {
+            Value::Converted(_, _, si) => *si,
+            Value::Radix(v, _) => *v,
+            v => v,
+        }
+    }
+
+    pub fn magnitude(self) -> Value {
+        match self {
+            Value::Quantity(v, _) => *v,
+            v => v,
+        }
+    }
+
+    fn numeric(&self, rhs: &Value, op: &str) -> Result<(), EvalErr> {
+        if self.is_bool() || rhs.is_bool() {
+            return Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be used in arithmetic, got {} {} {}", self, op, rhs
+            )));
         }
+        if self.is_symbolic() || rhs.is_symbolic() {
+            return Result::Err(EvalErr::TypeMismatch(format!(
+                "expressions cannot be used in arithmetic, got {} {} {}", self, op, rhs
+            )));
+        }
+        Result::Ok(())
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Value::Bool(_))
     }
 
-    pub fn div(self, rhs: Value) -> Value {
-        Value::Float(self.as_float() / rhs.as_float())
+    pub fn is_symbolic(&self) -> bool {
+        matches!(self, Value::Symbolic(_))
     }
 
-    pub fn rem(self, rhs: Value) -> Value {
+    pub fn equals(&self, rhs: &Value) -> Result<bool, EvalErr> {
         match (self, rhs) {
-            (Value::Int(a), Value::Int(b)) => Value::Int(a % b),
-            (a, b) => Value::Float(a.as_float() % b.as_float()),
+            (Value::Bool(a), Value::Bool(b)) => Result::Ok(a == b),
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be compared with numbers, got {} and {}", self, rhs
+            ))),
+            (a, b) if a.is_quantity() || b.is_quantity() => {
+                a.same_dim(b, "==")?;
+                a.clone().magnitude().equals(&b.clone().magnitude())
+            },
+            (a, b) if a.is_complex() || b.is_complex() => {
+                a.numeric(b, "==")?;
+                Result::Ok(a.as_complex() == b.as_complex())
+            },
+            (a, b) if a.is_interval() || b.is_interval() => {
+                a.numeric(b, "==")?;
+                let (x, y) = a.interval_pair(b)?;
+                Result::Ok(x == y)
+            },
+            (a, b) => Result::Ok(a.compare(b)? == Option::Some(Ordering::Equal)),
+        }
+    }
+
+    // None when the operands are unordered, as with NaN
+    pub fn compare(&self, rhs: &Value) -> Result<Option<Ordering>, EvalErr> {
+        if self.is_quantity() || rhs.is_quantity() {
+            self.same_dim(rhs, "vs")?;
+            return self.clone().magnitude().compare(&rhs.clone().magnitude());
+        }
+        if self.is_bool() || rhs.is_bool() {
+            return Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be ordered, got {} and {}", self, rhs
+            )));
+        }
+        if self.is_complex() || rhs.is_complex() {
+            return Result::Err(EvalErr::InvalidArgument(format!(
+                "complex numbers cannot be ordered, got {} and {}", self, rhs
+            )));
+        }
+        if self.is_interval() || rhs.is_interval() {
+            let (x, y) = self.interval_pair(rhs)?;
+            if x.hi() < y.lo() {
+                return Result::Ok(Option::Some(Ordering::Less));
+            }
+            if x.lo() > y.hi() {
+                return Result::Ok(Option::Some(Ordering::Greater));
+            }
+            if x == y && x.lo() == x.hi() {
+                return Result::Ok(Option::Some(Ordering::Equal));
+            }
+            return Result::Err(EvalErr::InvalidArgument(format!(
+                "comparison of overlapping intervals {} and {} is ambiguous", self, rhs
+            )));
+        }
+        if let Option::Some((x, y)) = self.big_pair(rhs) {
+            return Result::Ok(Option::Some(x.cmp(&y)));
+        }
+        match (self.as_ratio(), rhs.as_ratio()) {
+            (Option::Some(x), Option::Some(y)) => Result::Ok(Option::Some(x.cmp(&y))),
+            _ => Result::Ok(self.as_float().partial_cmp(&rhs.as_float())),
+        }
+    }
+
+    fn same_dim(&self, rhs: &Value, op: &str) -> Result<Dimension, EvalErr> {
+        if self.dim() != rhs.dim() {
+            return Result::Err(EvalErr::DimensionMismatch(format!("{} {} {}", self, op, rhs)));
+        }
+        Result::Ok(self.dim())
+    }
+
+    pub fn to_bigfloat(&self, prec: u64) -> Option<BigFloat> {
+        match self {
+            Value::Int(i) => Option::Some(BigFloat::from_integer(i, prec)),
+            Value::Float(f) => BigFloat::from_float(*f, prec),
+            Value::Rational(r) => Option::Some(BigFloat::from_ratio(r, prec)),
+            Value::Bool(_) | Value::Complex(_) | Value::Interval(_) | Value::Uncertain(_)
+            | Value::Quantity(..) | Value::Converted(..) | Value::Vector(_) | Value::Matrix(_) | Value::Symbolic(_) => {
+                Option::None
+            },
+            Value::BigFloat(b) => Option::Some(b.clone()),
+            Value::Radix(v, _) => v.to_bigfloat(prec),
+        }
+    }
+
+    pub fn is_bigfloat(&self) -> bool {
+        matches!(self, Value::BigFloat(_))
+    }
+
+    fn big_pair(&self, rhs: &Value) -> Option<(BigFloat, BigFloat)> {
+        let prec = match (self, rhs) {
+            (Value::BigFloat(a), Value::BigFloat(b)) => a.prec().max(b.prec()),
+            (Value::BigFloat(a), _) | (_, Value::BigFloat(a)) => a.prec(),
+            _ => return Option::None,
+        };
+        Option::Some((self.to_bigfloat(prec)?, rhs.to_bigfloat(prec)?))
+    }
+
+    pub fn as_complex(&self) -> Complex {
+        match self {
+            Value::Complex(c) => *c,
+            v => Complex::new(v.as_float(), 0.0),
+        }
+    }
+
+    pub fn is_complex(&self) -> bool {
+        matches!(self, Value::Complex(_))
+    }
+
+    pub fn as_ratio(&self) -> Option<Rational> {
+        match self {
+            Value::Int(i) => Option::Some(Rational::from_integer(i.clone())),
+            Value::Rational(r) => Option::Some(r.clone()),
+            Value::Radix(v, _) => v.as_ratio(),
+            Value::Bool(_) | Value::Float(_) | Value::Complex(_) | Value::BigFloat(_) | Value::Interval(_)
+            | Value::Uncertain(_) | Value::Quantity(..) | Value::Converted(..) | Value::Vector(_) | Value::Matrix(_)
+            | Value::Symbolic(_) => Option::None,
+        }
+    }
+
+    pub fn is_exact_zero(&self) -> bool {
+        match self {
+            Value::Int(i) => i.is_zero(),
+            Value::Rational(r) => r.is_zero(),
+            Value::Radix(v, _) => v.is_exact_zero(),
+            Value::Bool(_) | Value::Float(_) | Value::Complex(_) | Value::BigFloat(_) | Value::Interval(_)
+            | Value::Uncertain(_) | Value::Quantity(..) | Value::Converted(..) | Value::Vector(_) | Value::Matrix(_)
+            | Value::Symbolic(_) => false,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Value::Float(f) => *f == 0.0,
+            Value::Complex(c) => c.is_zero(),
+            Value::BigFloat(b) => b.is_zero(),
+            Value::Interval(iv) => iv.lo() == 0.0 && iv.hi() == 0.0,
+            Value::Uncertain(u) => u.value() == 0.0 && u.sigma() == 0.0,
+            Value::Quantity(v, _) => v.is_zero(),
+            Value::Converted(_, _, si) => si.is_zero(),
+            Value::Vector(items) => items.iter().all(Value::is_zero),
+            Value::Matrix(m) => m.items().iter().all(Value::is_zero),
+            v => v.is_exact_zero(),
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Value::Float(f) => f.is_finite(),
+            Value::Complex(c) => c.is_finite(),
+            Value::Interval(iv) => iv.is_finite(),
+            Value::Uncertain(u) => u.is_finite(),
+            Value::Quantity(v, _) => v.is_finite(),
+            Value::Converted(_, _, si) => si.is_finite(),
+            Value::Vector(items) => items.iter().all(Value::is_finite),
+            Value::Matrix(m) => m.items().iter().all(Value::is_finite),
+            _ => true,
+        }
+    }
+
+    pub fn into_decimal(self, precision: Option<u64>) -> Value {
+        match (self, precision) {
+            (Value::Rational(r), Option::Some(prec)) => Value::BigFloat(BigFloat::from_ratio(&r, prec)),
+            (Value::Rational(r), Option::None) => Value::Float(r.to_f64().unwrap_or(Float::NAN)),
+            (Value::Quantity(v, dim), precision) => Value::Quantity(Box::new(v.into_decimal(precision)), dim),
+            (Value::Converted(v, unit, si), precision) => Value::Converted(Box::new(v.into_decimal(precision)), unit, si),
+            (Value::Vector(items), precision) => {
+                Value::Vector(items.into_iter().map(|v| v.into_decimal(precision)).collect())
+            },
+            (Value::Matrix(m), precision) => Value::Matrix(m.map(|v| v.into_decimal(precision))),
+            (v, _) => v,
+        }
+    }
+
+    // shows floats that are a rounded form of a simple fraction as that fraction
+    pub fn into_fraction(self, max_den: &Integer) -> Value {
+        match self {
+            Value::Float(f) => match fraction::nearby(f, max_den) {
+                Option::Some(r) => Value::from_ratio(r),
+                Option::None => Value::Float(f),
+            },
+            Value::Vector(items) => Value::Vector(items.into_iter().map(|v| v.into_fraction(max_den)).collect()),
+            Value::Matrix(m) => Value::Matrix(m.map(|v| v.into_fraction(max_den))),
+            v => v,
+        }
+    }
+
+    pub fn add(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "+", |a, b| a.add(b, ctx));
+        }
+        self.numeric(&rhs, "+")?;
+        if self.is_quantity() || rhs.is_quantity() {
+            let dim = self.same_dim(&rhs, "+")?;
+            return Result::Ok(Value::quantity(self.magnitude().add(rhs.magnitude(), ctx)?, dim));
+        }
+        let value = match (self, rhs) {
+            (Value::Int(a), Value::Int(b)) => return Value::checked_int(a + b, ctx),
+            (a, b) if a.is_interval() || b.is_interval() => {
+                let (x, y) = a.interval_pair(&b)?;
+                Value::Interval(x.add(&y))
+            },
+            (a, b) if a.is_uncertain() || b.is_uncertain() => {
+                let (x, y) = a.uncertain_pair(&b)?;
+                Value::Uncertain(x.add(&y))
+            },
+            (a, b) if a.is_complex() || b.is_complex() => Value::from_complex(a.as_complex() + b.as_complex()),
+            (a, b) if a.is_bigfloat() || b.is_bigfloat() => match a.big_pair(&b) {
+                Option::Some((x, y)) => Value::BigFloat(x.add(&y)),
+                Option::None => Value::Float(a.as_float() + b.as_float()),
+            },
+            (a, b) => match (a.as_ratio(), b.as_ratio()) {
+                (Option::Some(x), Option::Some(y)) => Value::from_ratio(x + y),
+                _ => Value::Float(a.as_float() + b.as_float()),
+            },
+        };
+        Result::Ok(value)
+    }
+
+    pub fn sub(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "-", |a, b| a.sub(b, ctx));
+        }
+        self.numeric(&rhs, "-")?;
+        if self.is_quantity() || rhs.is_quantity() {
+            let dim = self.same_dim(&rhs, "-")?;
+            return Result::Ok(Value::quantity(self.magnitude().sub(rhs.magnitude(), ctx)?, dim));
+        }
+        let value = match (self, rhs) {
+            (Value::Int(a), Value::Int(b)) => return Value::checked_int(a - b, ctx),
+            (a, b) if a.is_interval() || b.is_interval() => {
+                let (x, y) = a.interval_pair(&b)?;
+                Value::Interval(x.sub(&y))
+            },
+            (a, b) if a.is_uncertain() || b.is_uncertain() => {
+                let (x, y) = a.uncertain_pair(&b)?;
+                Value::Uncertain(x.sub(&y))
+            },
+            (a, b) if a.is_complex() || b.is_complex() => Value::from_complex(a.as_complex() - b.as_complex()),
+            (a, b) if a.is_bigfloat() || b.is_bigfloat() => match a.big_pair(&b) {
+                Option::Some((x, y)) => Value::BigFloat(x.sub(&y)),
+                Option::None => Value::Float(a.as_float() - b.as_float()),
+            },
+            (a, b) => match (a.as_ratio(), b.as_ratio()) {
+                (Option::Some(x), Option::Some(y)) => Value::from_ratio(x - y),
+                _ => Value::Float(a.as_float() - b.as_float()),
+            },
+        };
+        Result::Ok(value)
+    }
+
+    pub fn mul(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_matrix() || rhs.is_matrix() {
+            return self.matrix_product(rhs, ctx);
+        }
+        if self.is_vector() || rhs.is_vector() {
+            return self.broadcast(rhs, "*", |a, b| a.mul(b, ctx));
+        }
+        self.numeric(&rhs, "*")?;
+        if self.is_quantity() || rhs.is_quantity() {
+            let dim = self.dim().mul(&rhs.dim())?;
+            return Result::Ok(Value::quantity(self.magnitude().mul(rhs.magnitude(), ctx)?, dim));
+        }
+        let value = match (self, rhs) {
+            (Value::Int(a), Value::Int(b)) if a.bits() + b.bits() > MAX_INTEGER_BITS + 1 => {
+                return Value::overflow(ctx, a.to_f64().unwrap_or_default() * b.to_f64().unwrap_or_default());
+            },
+            (Value::Int(a), Value::Int(b)) => return Value::checked_int(a * b, ctx),
+            (a, b) if a.is_interval() || b.is_interval() => {
+                let (x, y) = a.interval_pair(&b)?;
+                Value::Interval(x.mul(&y))
+            },
+            (a, b) if a.is_uncertain() || b.is_uncertain() => {
+                let (x, y) = a.uncertain_pair(&b)?;
+                Value::Uncertain(x.mul(&y))
+            },
+            (a, b) if a.is_complex() || b.is_complex() => Value::from_complex(a.as_complex() * b.as_complex()),
+            (a, b) if a.is_bigfloat() || b.is_bigfloat() => match a.big_pair(&b) {
+                Option::Some((x, y)) => Value::BigFloat(x.mul(&y)),
+                Option::None => Value::Float(a.as_float() * b.as_float()),
+            },
+            (a, b) => match (a.as_ratio(), b.as_ratio()) {
+                (Option::Some(x), Option::Some(y)) => Value::from_ratio(x * y),
+                _ => Value::Float(a.as_float() * b.as_float()),
+            },
+        };
+        Result::Ok(value)
+    }
+
+    pub fn div(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if let (Value::Matrix(a), Value::Matrix(b)) = (&self, &rhs) {
+            return Result::Ok(Value::Matrix(a.mul(&b.inverse(ctx)?, ctx)?));
+        }
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "/", |a, b| a.div(b, ctx));
+        }
+        self.numeric(&rhs, "/")?;
+        if self.is_quantity() || rhs.is_quantity() {
+            let dim = self.dim().div(&rhs.dim())?;
+            return Result::Ok(Value::quantity(self.magnitude().div(rhs.magnitude(), ctx)?, dim));
+        }
+        if let (Option::Some(_), Value::Int(a), Value::Int(b)) = (ctx.int_width, &self, &rhs) {
+            if b.is_zero() {
+                return Result::Err(EvalErr::DivisionByZero(format!("{} / {}", a, b)));
+            }
+            // fixed-width division truncates like the machine integers it models
+            return Value::checked_int(a / b, ctx);
+        }
+        if ctx.strict_float && rhs.is_zero() {
+            return Result::Err(EvalErr::DivisionByZero(format!("{} / {}", self, rhs)));
+        }
+        if self.is_interval() || rhs.is_interval() {
+            let (x, y) = self.interval_pair(&rhs)?;
+            return match x.div(&y) {
+                Option::Some(v) => Result::Ok(Value::Interval(v)),
+                Option::None => Result::Err(EvalErr::DivisionByZero(format!("{} / {}", self, rhs))),
+            };
+        }
+        if self.is_uncertain() || rhs.is_uncertain() {
+            let (x, y) = self.uncertain_pair(&rhs)?;
+            return match x.div(&y) {
+                Option::Some(v) => Result::Ok(Value::Uncertain(v)),
+                Option::None => Result::Err(EvalErr::DivisionByZero(format!("{} / {}", self, rhs))),
+            };
+        }
+        if self.is_complex() || rhs.is_complex() {
+            return Result::Ok(Value::from_complex(self.as_complex() / rhs.as_complex()));
+        }
+        if rhs.is_exact_zero() {
+            return Result::Ok(Value::Float(self.as_float() / rhs.as_float()));
+        }
+        if let Option::Some((x, y)) = self.big_pair(&rhs) {
+            if !y.is_zero() {
+                return Result::Ok(Value::BigFloat(x.div(&y)));
+            }
+        }
+        match (self.as_ratio(), rhs.as_ratio()) {
+            (Option::Some(x), Option::Some(y)) => Result::Ok(Value::from_ratio(x / y)),
+            _ => Result::Ok(Value::Float(self.as_float() / rhs.as_float())),
         }
     }
 
-    pub fn pow(self, rhs: Value) -> Value {
-        Value::Float(self.as_float().powf(rhs.as_float()))
+    pub fn rem(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "%", |a, b| a.rem(b, ctx));
+        }
+        self.numeric(&rhs, "%")?;
+        if self.is_quantity() || rhs.is_quantity() {
+            let dim = self.same_dim(&rhs, "%")?;
+            return Result::Ok(Value::quantity(self.magnitude().rem(rhs.magnitude(), ctx)?, dim));
+        }
+        let value = match (self, rhs) {
+            (a, b) if b.is_exact_zero() && a.as_ratio().is_some() => {
+                if ctx.promote_to_float {
+                    return Result::Ok(Value::Float(Float::NAN));
+                }
+                return Result::Err(EvalErr::DivisionByZero(format!("{} % {}", a, b)));
+            },
+            (Value::Int(a), Value::Int(b)) => Value::Int(a % b),
+            (a, b) if a.is_interval() || b.is_interval() => {
+                return Result::Err(EvalErr::InvalidArgument(format!(
+                    "remainder is not defined for intervals, got {} % {}", a, b
+                )));
+            },
+            (a, b) if a.is_uncertain() || b.is_uncertain() => {
+                let (x, y) = a.uncertain_pair(&b)?;
+                match x.rem(&y) {
+                    Option::Some(v) => Value::Uncertain(v),
+                    Option::None => return Result::Err(EvalErr::DivisionByZero(format!("{} % {}", a, b))),
+                }
+            },
+            (a, b) if a.is_complex() || b.is_complex() => Value::from_complex(a.as_complex() % b.as_complex()),
+            (a, b) if b.is_exact_zero() => Value::Float(a.as_float() % b.as_float()),
+            (a, b) if a.is_bigfloat() || b.is_bigfloat() => match a.big_pair(&b) {
+                Option::Some((x, y)) if !y.is_zero() => Value::BigFloat(x.rem(&y)),
+                _ => Value::Float(a.as_float() % b.as_float()),
+            },
+            (a, b) => match (a.as_ratio(), b.as_ratio()) {
+                (Option::Some(x), Option::Some(y)) => Value::from_ratio(x % y),
+                _ => Value::Float(a.as_float() % b.as_float()),
+            },
+        };
+        Result::Ok(value)
     }
 
-    pub fn neg(self) -> Value {
+    pub fn pow(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_matrix() || rhs.is_matrix() {
+            return match (&self, &rhs) {
+                (Value::Matrix(m), Value::Int(n)) => Result::Ok(Value::Matrix(m.powi(n, ctx)?)),
+                _ => Result::Err(EvalErr::InvalidArgument(format!(
+                    "matrices can only be raised to integer powers, got {} ** {}", self, rhs
+                ))),
+            };
+        }
+        if self.is_vector() || rhs.is_vector() {
+            return self.broadcast(rhs, "**", |a, b| a.pow(b, ctx));
+        }
+        self.numeric(&rhs, "**")?;
+        if self.is_quantity() || rhs.is_quantity() {
+            let dim = self.pow_dim(&rhs)?;
+            return Result::Ok(Value::quantity(self.magnitude().pow(rhs, ctx)?, dim));
+        }
+        if let (Option::Some(width), Value::Int(a), Value::Int(b)) = (ctx.int_width, &self, &rhs) {
+            return Value::fixed_pow(a, b, width, ctx);
+        }
+        if self.is_interval() || rhs.is_interval() {
+            let (x, y) = self.interval_pair(&rhs)?;
+            let value = match &rhs {
+                Value::Int(n) => match n.to_i32() {
+                    Option::Some(n) => x.powi(n),
+                    Option::None => Option::None,
+                },
+                _ => x.powf(&y),
+            };
+            return match value {
+                Option::Some(v) => Result::Ok(Value::Interval(v)),
+                Option::None => Result::Err(EvalErr::InvalidArgument(format!(
+                    "{} ** {} has no bounded enclosure", self, rhs
+                ))),
+            };
+        }
+        if self.is_uncertain() || rhs.is_uncertain() {
+            let (x, y) = self.uncertain_pair(&rhs)?;
+            return match x.pow(&y) {
+                Option::Some(v) => Result::Ok(Value::Uncertain(v)),
+                Option::None => Result::Err(EvalErr::InvalidArgument(format!(
+                    "{} ** {} is not a real number", self, rhs
+                ))),
+            };
+        }
+        if self.is_complex() || rhs.is_complex() {
+            // repeated multiplication keeps integer powers of i exact
+            if let Option::Some(n) = rhs.as_ratio().filter(Rational::is_integer).and_then(|r| r.numer().to_i32()) {
+                return Result::Ok(Value::from_complex(self.as_complex().powi(n)));
+            }
+            return Result::Ok(Value::from_complex(self.as_complex().powc(rhs.as_complex())));
+        }
+        if let Option::Some((x, y)) = self.big_pair(&rhs) {
+            if x.pow_overflows(&y) {
+                return Result::Err(EvalErr::Overflow(format!(
+                    "{} ** {} is out of the precise exponent range", self, rhs
+                )));
+            }
+            if let Option::Some(n) = y.is_integer().then(|| y.trunc().to_i64()).flatten() {
+                if n >= 0 || !x.is_zero() {
+                    return Result::Ok(Value::BigFloat(x.powi(n)));
+                }
+            }
+            if let Option::Some(v) = x.powf(&y) {
+                return Result::Ok(Value::BigFloat(v));
+            }
+        }
+        if let (Option::Some(base), Value::Int(b)) = (self.as_ratio(), &rhs) {
+            if !b.is_negative() || !base.is_zero() {
+                return Value::checked_ratio_pow(base, b, ctx);
+            }
+        }
+        let (base, exp) = (self.as_float(), rhs.as_float());
+        if base < 0.0 && exp.fract() != 0.0 {
+            return Result::Ok(Value::from_complex(Complex::new(base, 0.0).powf(exp)));
+        }
+        Result::Ok(Value::Float(base.powf(exp)))
+    }
+
+    pub fn neg(self, ctx: &Context) -> Result<Value, EvalErr> {
         match self {
-            Value::Int(i) => Value::Int(-i),
-            Value::Float(f) => Value::Float(-f),
+            Value::Bool(b) => Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be used in arithmetic, got -{}", b
+            ))),
+            Value::Quantity(v, dim) => Result::Ok(Value::Quantity(Box::new(v.neg(ctx)?), dim)),
+            Value::Converted(_, _, si) => si.neg(ctx),
+            Value::Radix(v, _) => v.neg(ctx),
+            Value::Int(i) => Value::checked_int(-i, ctx),
+            Value::Float(f) => Result::Ok(Value::Float(-f)),
+            Value::Rational(r) => Result::Ok(Value::Rational(-r)),
+            Value::Complex(c) => Result::Ok(Value::Complex(-c)),
+            Value::BigFloat(b) => Result::Ok(Value::BigFloat(b.neg())),
+            Value::Interval(iv) => Result::Ok(Value::Interval(iv.neg())),
+            Value::Uncertain(u) => Result::Ok(Value::Uncertain(u.neg())),
+            Value::Vector(items) => Value::map_items(items, |v| v.neg(ctx)),
+            Value::Matrix(m) => Result::Ok(Value::Matrix(m.try_map(|v| v.neg(ctx))?)),
+            Value::Symbolic(e) => Result::Err(EvalErr::TypeMismatch(format!(
+                "expressions cannot be used in arithmetic, got -({})", e
+            ))),
+        }
+    }
+
+    // the context only reaches the elements of arrays and quantities, like
+    // that of the other binary operators
+    #[allow(clippy::only_used_in_recursion)]
+    pub fn plus_minus(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "±", |a, b| a.plus_minus(b, ctx));
+        }
+        self.numeric(&rhs, "±")?;
+        if self.is_quantity() || rhs.is_quantity() {
+            let dim = self.same_dim(&rhs, "±")?;
+            return Result::Ok(Value::quantity(self.magnitude().plus_minus(rhs.magnitude(), ctx)?, dim));
+        }
+        if rhs.is_complex() || rhs.is_interval() || rhs.is_uncertain() {
+            return Result::Err(EvalErr::InvalidArgument(format!(
+                "uncertainty must be a real number, got {}", rhs
+            )));
+        }
+        let sigma = rhs.as_float();
+        if sigma.is_nan() || sigma < 0.0 {
+            return Result::Err(EvalErr::InvalidArgument(format!(
+                "uncertainty must be non-negative, got {}", rhs
+            )));
+        }
+        // an already uncertain value gains an independent error term
+        match self.as_uncertain() {
+            Option::Some(u) => Result::Ok(Value::Uncertain(u.hypot(sigma))),
+            Option::None => Result::Err(EvalErr::InvalidArgument(format!(
+                "uncertainty can only be attached to real numbers, got {}", self
+            ))),
+        }
+    }
+
+    fn pow_dim(&self, rhs: &Value) -> Result<Dimension, EvalErr> {
+        let err = || EvalErr::DimensionMismatch(format!(
+            "{} ** {} needs a dimensionless rational exponent", self, rhs
+        ));
+        if rhs.is_quantity() {
+            return Result::Err(err());
+        }
+        // a float exponent counts when it is exactly a ratio with a small
+        // denominator, as 0.5 and 1.5 are
+        let exp = match rhs {
+            Value::Float(f) => Rational::from_float(*f),
+            v => v.as_ratio(),
+        };
+        let exp = exp.ok_or_else(err)?;
+        match (exp.numer().to_i32(), exp.denom().to_i32()) {
+            (Option::Some(p), Option::Some(q)) => self.dim().root(q).ok_or_else(|| {
+                EvalErr::DimensionMismatch(format!("{} ** {} has fractional dimensions", self, rhs))
+            })?.powi(p),
+            _ => Result::Err(err()),
+        }
+    }
+
+    pub fn is_vector(&self) -> bool {
+        matches!(self, Value::Vector(_))
+    }
+
+    pub fn is_matrix(&self) -> bool {
+        matches!(self, Value::Matrix(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.is_vector() || self.is_matrix()
+    }
+
+    // a literal whose elements are all vectors of one length is a matrix
+    pub fn from_items(items: Vec<Value>) -> Result<Value, EvalErr> {
+        if !items.iter().any(Value::is_array) {
+            return Result::Ok(Value::Vector(items));
+        }
+        let literal = Value::Vector(items.clone());
+        let rows: Vec<Vec<Value>> = items
+            .into_iter()
+            .map(|v| match v {
+                Value::Vector(row) => Result::Ok(row),
+                _ => Result::Err(EvalErr::ShapeMismatch(format!(
+                    "every row of a matrix must be a vector, got {}", literal
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match Matrix::from_rows(rows) {
+            Option::Some(m) => Result::Ok(Value::Matrix(m)),
+            Option::None => Result::Err(EvalErr::ShapeMismatch(format!(
+                "rows of a matrix must have one non-zero length, got {}", literal
+            ))),
+        }
+    }
+
+    // `*` on a matrix is the matrix product, with a vector read as a column
+    // on the right or as a row on the left
+    fn matrix_product(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        let shape_err = |a: &Value, b: &Value| EvalErr::ShapeMismatch(format!("cannot multiply {} by {}", a, b));
+        match (&self, &rhs) {
+            (Value::Matrix(a), Value::Matrix(b)) => Result::Ok(Value::Matrix(a.mul(b, ctx)?)),
+            (Value::Matrix(a), Value::Vector(b)) => {
+                let column = Matrix::column(b.clone()).ok_or_else(|| shape_err(&self, &rhs))?;
+                Result::Ok(Value::Vector(a.mul(&column, ctx)?.into_items()))
+            },
+            (Value::Vector(a), Value::Matrix(b)) => {
+                let row = Matrix::from_rows(vec![a.clone()]).ok_or_else(|| shape_err(&self, &rhs))?;
+                Result::Ok(Value::Vector(row.mul(b, ctx)?.into_items()))
+            },
+            _ => self.broadcast(rhs, "*", |a, b| a.mul(b, ctx)),
+        }
+    }
+
+    pub fn map_items<F>(items: Vec<Value>, f: F) -> Result<Value, EvalErr>
+    where
+        F: Fn(Value) -> Result<Value, EvalErr>,
+    {
+        Result::Ok(Value::Vector(items.into_iter().map(f).collect::<Result<Vec<_>, _>>()?))
+    }
+
+    // applies a scalar operation element-wise, repeating a scalar operand
+    // across every element of a vector
+    pub fn broadcast<F>(self, rhs: Value, op: &str, f: F) -> Result<Value, EvalErr>
+    where
+        F: Fn(Value, Value) -> Result<Value, EvalErr>,
+    {
+        match (self, rhs) {
+            (Value::Vector(a), Value::Vector(b)) if a.len() != b.len() => {
+                Result::Err(EvalErr::LengthMismatch(format!(
+                    "{} {} {}", Value::Vector(a), op, Value::Vector(b)
+                )))
+            },
+            (Value::Vector(a), Value::Vector(b)) => {
+                let items = a.into_iter().zip(b).map(|(x, y)| f(x, y));
+                Result::Ok(Value::Vector(items.collect::<Result<Vec<_>, _>>()?))
+            },
+            (Value::Matrix(a), Value::Matrix(b)) => Result::Ok(Value::Matrix(a.zip_with(b, op, f)?)),
+            (a, b) if a.is_array() && b.is_array() => {
+                Result::Err(EvalErr::ShapeMismatch(format!("{} {} {}", a, op, b)))
+            },
+            (Value::Matrix(a), b) => Result::Ok(Value::Matrix(a.try_map(|x| f(x, b.clone()))?)),
+            (a, Value::Matrix(b)) => Result::Ok(Value::Matrix(b.try_map(|y| f(a.clone(), y))?)),
+            (Value::Vector(a), b) => Value::map_items(a, |x| f(x, b.clone())),
+            (a, Value::Vector(b)) => Value::map_items(b, |y| f(a.clone(), y)),
+            (a, b) => f(a, b),
+        }
+    }
+
+    pub fn overflow(ctx: &Context, fallback: Float) -> Result<Value, EvalErr> {
+        if ctx.promote_to_float {
+            return Result::Ok(Value::Float(fallback));
+        }
+        Result::Err(EvalErr::Overflow(format!(
+            "integer result exceeds {} bits", MAX_INTEGER_BITS
+        )))
+    }
+
+    // reduces an integer to the fixed width, if one is set, keeping the low
+    // bits as two's complement or unsigned
+    pub fn wrap(i: Integer, ctx: &Context) -> Integer {
+        let Option::Some(width) = ctx.int_width else {
+            return i;
+        };
+        let modulus = Integer::one() << width;
+        let wrapped = i.mod_floor(&modulus);
+        if !ctx.unsigned && wrapped.bit(width as u64 - 1) {
+            return wrapped - modulus;
+        }
+        wrapped
+    }
+
+    fn width_overflow(result: String, width: u32, ctx: &Context) -> EvalErr {
+        let kind = if ctx.unsigned { "an unsigned" } else { "a signed" };
+        EvalErr::Overflow(format!("{} does not fit in {} {}-bit integer", result, kind, width))
+    }
+
+    fn fixed_pow(base: &Integer, exp: &Integer, width: u32, ctx: &Context) -> Result<Value, EvalErr> {
+        // 1 / base**n truncates like fixed-width division, leaving only 1 and -1
+        if exp.is_negative() {
+            if base.is_zero() {
+                return Result::Err(EvalErr::DivisionByZero(format!("{} ** {}", base, exp)));
+            }
+            if base.abs().is_one() {
+                return Value::checked_int(if exp.is_even() { Integer::one() } else { base.clone() }, ctx);
+            }
+            return Result::Ok(Value::Int(Integer::zero()));
+        }
+        let modulus = Integer::one() << width;
+        if !ctx.report_overflow {
+            return Result::Ok(Value::Int(Value::wrap(base.mod_floor(&modulus).modpow(exp, &modulus), ctx)));
+        }
+        // |base| >= 2**(bits - 1), so a larger product of exponents cannot fit
+        match exp.to_u32() {
+            Option::Some(n) if (base.bits() - 1).saturating_mul(n as u64) <= width as u64 => {
+                Value::checked_int(base.pow(n), ctx)
+            },
+            _ => Result::Err(Value::width_overflow(format!("{} ** {}", base, exp), width, ctx)),
+        }
+    }
+
+    pub fn checked_int(i: Integer, ctx: &Context) -> Result<Value, EvalErr> {
+        if let Option::Some(width) = ctx.int_width {
+            let wrapped = Value::wrap(i.clone(), ctx);
+            if ctx.report_overflow && wrapped != i {
+                return Result::Err(Value::width_overflow(i.to_string(), width, ctx));
+            }
+            return Result::Ok(Value::Int(wrapped));
+        }
+        if i.bits() > MAX_INTEGER_BITS {
+            return Value::overflow(ctx, i.to_f64().unwrap_or(Float::INFINITY));
+        }
+        Result::Ok(Value::Int(i))
+    }
+
+    fn checked_ratio_pow(base: Rational, exp: &Integer, ctx: &Context) -> Result<Value, EvalErr> {
+        let base_bits = base.numer().bits().max(base.denom().bits());
+        if exp.is_zero() {
+            return Result::Ok(Value::Int(Integer::one()));
+        }
+        if base_bits <= 1 && base.denom().is_one() {
+            // 0, 1 and -1 stay small for any exponent
+            let value = if exp.is_even() { base.abs() } else { base };
+            return Result::Ok(Value::from_ratio(value));
+        }
+
+        let fallback = || base.to_f64().unwrap_or_default().powf(exp.to_f64().unwrap_or(Float::INFINITY));
+        match exp.to_i32() {
+            Option::Some(n) if base_bits.saturating_mul(n.unsigned_abs() as u64) <= MAX_INTEGER_BITS => {
+                Result::Ok(Value::from_ratio(base.pow(n)))
+            },
+            _ => Value::overflow(ctx, fallback()),
         }
     }
 
     pub fn not(self, ctx: &Context) -> Result<Value, EvalErr> {
         if let Value::Int(i) = self {
-            return Result::Ok(Value::Int(!i));
+            return Result::Ok(Value::Int(Value::wrap(!i, ctx)));
+        }
+        if let Value::Bool(b) = self {
+            return Result::Ok(Value::Bool(!b));
+        }
+        if let Value::Vector(items) = self {
+            return Value::map_items(items, |v| v.not(ctx));
+        }
+        if let Value::Matrix(m) = self {
+            return Result::Ok(Value::Matrix(m.try_map(|v| v.not(ctx))?));
         }
 
+        self.bitwise_operand()?;
+
         if ctx.allow_floating_bitwise_operations {
-            return Result::Ok(Value::Int(self.as_int()));
+            return Result::Ok(Value::Int(Value::wrap(!self.as_int(), ctx)));
         }
 
         Result::Err(EvalErr::InvalidFloatingPointOperation(
@@ -107,12 +981,21 @@ impl Value {
     }
 
     pub fn and(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "&", |a, b| a.and(b, ctx));
+        }
         let allow_fp_bw_ops = ctx.allow_floating_bitwise_operations;
+        self.bitwise_operand()?;
+        rhs.bitwise_operand()?;
         match (&self, &rhs) {
-            (&Value::Int(i1), &Value::Int(i2)) => Result::Ok(Value::Int(i1 & i2)),
-            (&Value::Int(i), &Value::Float(_)) if allow_fp_bw_ops => Result::Ok(Value::Int(i & rhs.as_int())),
-            (&Value::Float(_), &Value::Int(i)) if allow_fp_bw_ops => Result::Ok(Value::Int(self.as_int() & i)),
-            (&Value::Float(_), &Value::Float(_)) if allow_fp_bw_ops => Result::Ok(Value::Int(self.as_int() & rhs.as_int())),
+            (Value::Bool(b1), Value::Bool(b2)) => Result::Ok(Value::Bool(b1 & b2)),
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be mixed with numbers, got {} & {}", self, rhs
+            ))),
+            (Value::Int(i1), Value::Int(i2)) => Result::Ok(Value::Int(Value::wrap(i1 & i2, ctx))),
+            (Value::Int(i), _) if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(i & rhs.as_int(), ctx))),
+            (_, Value::Int(i)) if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(self.as_int() & i, ctx))),
+            _ if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(self.as_int() & rhs.as_int(), ctx))),
             _ => Result::Err(EvalErr::InvalidFloatingPointOperation(
                 "Bitwise operator cannot be operated on floating point numbers".to_string()
             ))
@@ -120,12 +1003,21 @@ impl Value {
     }
 
     pub fn or(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "|", |a, b| a.or(b, ctx));
+        }
         let allow_fp_bw_ops = ctx.allow_floating_bitwise_operations;
+        self.bitwise_operand()?;
+        rhs.bitwise_operand()?;
         match (&self, &rhs) {
-            (&Value::Int(i1), &Value::Int(i2)) => Result::Ok(Value::Int(i1 | i2)),
-            (&Value::Int(i), &Value::Float(_)) if allow_fp_bw_ops => Result::Ok(Value::Int(i | rhs.as_int())),
-            (&Value::Float(_), &Value::Int(i)) if allow_fp_bw_ops => Result::Ok(Value::Int(self.as_int() | i)),
-            (&Value::Float(_), &Value::Float(_)) if allow_fp_bw_ops => Result::Ok(Value::Int(self.as_int() | rhs.as_int())),
+            (Value::Bool(b1), Value::Bool(b2)) => Result::Ok(Value::Bool(b1 | b2)),
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be mixed with numbers, got {} | {}", self, rhs
+            ))),
+            (Value::Int(i1), Value::Int(i2)) => Result::Ok(Value::Int(Value::wrap(i1 | i2, ctx))),
+            (Value::Int(i), _) if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(i | rhs.as_int(), ctx))),
+            (_, Value::Int(i)) if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(self.as_int() | i, ctx))),
+            _ if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(self.as_int() | rhs.as_int(), ctx))),
             _ => Result::Err(EvalErr::InvalidFloatingPointOperation(
                 "Bitwise operator cannot be operated on floating point numbers".to_string()
             ))
@@ -133,24 +1025,205 @@ impl Value {
     }
 
     pub fn xor(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "^", |a, b| a.xor(b, ctx));
+        }
         let allow_fp_bw_ops = ctx.allow_floating_bitwise_operations;
+        self.bitwise_operand()?;
+        rhs.bitwise_operand()?;
         match (&self, &rhs) {
-            (&Value::Int(i1), &Value::Int(i2)) => Result::Ok(Value::Int(i1 ^ i2)),
-            (&Value::Int(i), &Value::Float(_)) if allow_fp_bw_ops => Result::Ok(Value::Int(i ^ rhs.as_int())),
-            (&Value::Float(_), &Value::Int(i)) if allow_fp_bw_ops => Result::Ok(Value::Int(self.as_int() ^ i)),
-            (&Value::Float(_), &Value::Float(_)) if allow_fp_bw_ops => Result::Ok(Value::Int(self.as_int() ^ rhs.as_int())),
+            (Value::Bool(b1), Value::Bool(b2)) => Result::Ok(Value::Bool(b1 ^ b2)),
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be mixed with numbers, got {} ^ {}", self, rhs
+            ))),
+            (Value::Int(i1), Value::Int(i2)) => Result::Ok(Value::Int(Value::wrap(i1 ^ i2, ctx))),
+            (Value::Int(i), _) if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(i ^ rhs.as_int(), ctx))),
+            (_, Value::Int(i)) if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(self.as_int() ^ i, ctx))),
+            _ if allow_fp_bw_ops => Result::Ok(Value::Int(Value::wrap(self.as_int() ^ rhs.as_int(), ctx))),
             _ => Result::Err(EvalErr::InvalidFloatingPointOperation(
                 "Bitwise operator cannot be operated on floating point numbers".to_string()
             ))
         }
     }
+
+    pub fn shl(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, "<<", |a, b| a.shl(b, ctx));
+        }
+        let (value, amount) = self.shift_operands(&rhs, "<<", ctx)?;
+        match ctx.int_width {
+            // past the width every bit has been shifted out
+            Option::Some(width) => Value::checked_int(value << amount.min(width as u64), ctx),
+            Option::None if !value.is_zero() && value.bits().saturating_add(amount) > MAX_INTEGER_BITS => {
+                let fallback = value.to_f64().unwrap_or_default() * Float::powf(2.0, amount as Float);
+                Value::overflow(ctx, fallback)
+            },
+            Option::None => Value::checked_int(value << amount, ctx),
+        }
+    }
+
+    // an arithmetic shift, so negative numbers keep their sign
+    pub fn shr(self, rhs: Value, ctx: &Context) -> Result<Value, EvalErr> {
+        if self.is_array() || rhs.is_array() {
+            return self.broadcast(rhs, ">>", |a, b| a.shr(b, ctx));
+        }
+        let (value, amount) = self.shift_operands(&rhs, ">>", ctx)?;
+        let amount = amount.min(value.bits() + 1);
+        Value::checked_int(value >> amount, ctx)
+    }
+
+    fn shift_operands(&self, rhs: &Value, op: &str, ctx: &Context) -> Result<(Integer, u64), EvalErr> {
+        self.bitwise_operand()?;
+        rhs.bitwise_operand()?;
+        if self.is_bool() || rhs.is_bool() {
+            return Result::Err(EvalErr::TypeMismatch(format!(
+                "booleans cannot be shifted, got {} {} {}", self, op, rhs
+            )));
+        }
+        let (value, amount) = match (self, rhs) {
+            (Value::Int(a), Value::Int(b)) => (a.clone(), b.clone()),
+            _ if ctx.allow_floating_bitwise_operations => (self.as_int(), rhs.as_int()),
+            _ => return Result::Err(EvalErr::InvalidFloatingPointOperation(
+                "Bitwise operator cannot be operated on floating point numbers".to_string()
+            )),
+        };
+        if amount.is_negative() {
+            return Result::Err(EvalErr::InvalidArgument(format!(
+                "shift amount must be non-negative, got {} {} {}", self, op, rhs
+            )));
+        }
+        Result::Ok((value, amount.to_u64().unwrap_or(u64::MAX)))
+    }
+
+    // an integer in base 2, 8, 10 or 16 with the prefix the lexer reads back;
+    // given a width, negative numbers show their two's complement
+    pub fn fmt_radix(&self, base: u32, width: Option<u32>) -> String {
+        let i = match self {
+            Value::Int(i) => i,
+            Value::Radix(v, _) => return v.fmt_radix(base, width),
+            Value::Vector(items) => {
+                let items: Vec<String> = items.iter().map(|v| v.fmt_radix(base, width)).collect();
+                return format!("[{}]", items.join(", "));
+            },
+            Value::Matrix(m) => {
+                let rows: Vec<String> = (0..m.rows()).map(|i| Value::Vector(m.row(i)).fmt_radix(base, width)).collect();
+                return format!("[{}]", rows.join(", "));
+            },
+            v => return format!("{}", v),
+        };
+        let prefix = match base {
+            2 => "0b",
+            8 => "0o",
+            16 => "0x",
+            _ => "",
+        };
+        match width {
+            Option::Some(width) => {
+                let pattern = i.mod_floor(&(Integer::one() << width));
+                format!("{}{}", prefix, pattern.to_str_radix(base))
+            },
+            Option::None if i.is_negative() => format!("-{}{}", prefix, i.abs().to_str_radix(base)),
+            Option::None => format!("{}{}", prefix, i.to_str_radix(base)),
+        }
+    }
+
+    // writes the numbers inside a value through a display format
+    pub fn fmt_with(&self, format: &Format) -> String {
+        match self {
+            Value::Int(i) => format.decimal(&i.to_string()),
+            Value::Float(f) => format.decimal(&f.to_string()),
+            Value::BigFloat(b) => format.decimal(&b.to_string()),
+            Value::Rational(_) => self.clone().into_decimal(Option::None).fmt_with(format),
+            // the ends round outwards so the interval still encloses the value
+            Value::Interval(v) => format!(
+                "[{}, {}]",
+                format.decimal_rounded(&v.lo().to_string(), RoundingMode::Floor),
+                format.decimal_rounded(&v.hi().to_string(), RoundingMode::Ceiling)
+            ),
+            Value::Uncertain(u) => {
+                format!("{} ± {}", format.decimal(&u.value().to_string()), format.decimal(&u.sigma().to_string()))
+            },
+            Value::Complex(c) if c.re == 0.0 => format!("{}i", format.decimal(&c.im.to_string())),
+            Value::Complex(c) => {
+                let im = format.decimal(&c.im.to_string());
+                let sign = if im.starts_with('-') { "" } else { "+" };
+                format!("{}{}{}i", format.decimal(&c.re.to_string()), sign, im)
+            },
+            Value::Quantity(v, dim) => format!("{} {}", v.clone().into_decimal(Option::None).fmt_with(format), dim),
+            Value::Converted(v, unit, _) => {
+                format!("{} {}", v.clone().into_decimal(Option::None).fmt_with(format), unit)
+            },
+            Value::Vector(items) => {
+                let items: Vec<String> = items.iter().map(|v| v.fmt_with(format)).collect();
+                format!("[{}]", items.join(", "))
+            },
+            Value::Matrix(m) => {
+                let rows: Vec<String> = (0..m.rows()).map(|i| Value::Vector(m.row(i)).fmt_with(format)).collect();
+                format!("[{}]", rows.join(", "))
+            },
+            v => format!("{}", v),
+        }
+    }
+
+    // a fixed-width integer followed by its bit pattern in hex
+    pub fn fmt_bits(&self, width: u32) -> String {
+        match self {
+            Value::Int(i) => {
+                let pattern = i.mod_floor(&(Integer::one() << width)).to_str_radix(16).to_uppercase();
+                format!("{} (0x{:0>2$})", i, pattern, width.div_ceil(4) as usize)
+            },
+            v => format!("{}", v),
+        }
+    }
+
+    pub fn fmt_polar(&self) -> String {
+        match self {
+            Value::Complex(c) => {
+                let (r, theta) = c.to_polar();
+                format!("{}∠{}", r, theta)
+            },
+            v => format!("{}", v),
+        }
+    }
+
+    fn bitwise_operand(&self) -> Result<(), EvalErr> {
+        let kind = match self {
+            Value::Complex(_) => "complex numbers",
+            Value::Interval(_) => "intervals",
+            Value::Uncertain(_) => "uncertain values",
+            Value::Quantity(..) | Value::Converted(..) => "quantities",
+            Value::Vector(_) | Value::Matrix(_) => "vectors",
+            Value::Symbolic(_) => "expressions",
+            _ => return Result::Ok(()),
+        };
+        Result::Err(EvalErr::InvalidArgument(format!(
+            "Bitwise operator cannot be operated on {}", kind
+        )))
+    }
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Value::Bool(v) => write!(f, "{}", v),
             Value::Int(v) => write!(f, "{}", v),
             Value::Float(v) => write!(f, "{}", v),
+            Value::Rational(v) => write!(f, "{}", v),
+            Value::Complex(v) if v.re == 0.0 => write!(f, "{}i", v.im),
+            Value::Complex(v) => write!(f, "{}{:+}i", v.re, v.im),
+            Value::BigFloat(v) => write!(f, "{}", v),
+            Value::Interval(v) => write!(f, "{}", v),
+            Value::Uncertain(v) => write!(f, "{}", v),
+            // measurements read better as decimals than as exact fractions
+            Value::Quantity(v, dim) => write!(f, "{} {}", v.clone().into_decimal(Option::None), dim),
+            Value::Converted(v, unit, _) => write!(f, "{} {}", v.clone().into_decimal(Option::None), unit),
+            Value::Radix(v, base) => write!(f, "{}", v.fmt_radix(*base, Option::None)),
+            Value::Vector(items) => {
+                let items: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            },
+            Value::Matrix(m) => write!(f, "{}", m),
+            Value::Symbolic(e) => write!(f, "{}", e),
         }
     }
 }
@@ -158,8 +1231,95 @@ impl fmt::Display for Value {
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Value::Bool(v) => write!(f, "Value::BOOL({})", v),
             Value::Int(v) => write!(f, "Value::INT({})", v),
             Value::Float(v) => write!(f, "Value::FLOAT({})", v),
+            Value::Rational(v) => write!(f, "Value::RATIONAL({})", v),
+            Value::Complex(v) => write!(f, "Value::COMPLEX({})", v),
+            Value::BigFloat(v) => write!(f, "Value::BIGFLOAT({})", v),
+            Value::Interval(v) => write!(f, "Value::INTERVAL({})", v),
+            Value::Uncertain(v) => write!(f, "Value::UNCERTAIN({})", v),
+            Value::Quantity(v, dim) => write!(f, "Value::QUANTITY({:?} {})", v, dim),
+            Value::Converted(v, unit, _) => write!(f, "Value::CONVERTED({:?} {})", v, unit),
+            Value::Radix(v, base) => write!(f, "Value::RADIX({:?} base {})", v, base),
+            Value::Vector(items) => write!(f, "Value::VECTOR({:?})", items),
+            Value::Matrix(m) => write!(f, "Value::MATRIX({})", m),
+            Value::Symbolic(e) => write!(f, "Value::SYMBOLIC({})", e),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::format::Notation;
+
+    fn format(decimals: Option<usize>, significant: Option<usize>) -> Format {
+        Format { notation: Notation::Plain, decimals, significant, separators: false }
+    }
+
+    #[test]
+    fn formatted_parts() {
+        let third = Value::from_ratio(Rational::new(Integer::one(), Integer::from(3)));
+        assert_eq!(third.fmt_with(&format(Option::Some(2), Option::None)), "0.33");
+        assert_eq!(third.fmt_with(&format(Option::None, Option::Some(3))), "0.333");
+        // interval ends round outwards
+        let iv = Value::Interval(Interval::new(1.0 / 3.0, 2.0 / 3.0).unwrap());
+        assert_eq!(iv.fmt_with(&format(Option::Some(2), Option::None)), "[0.33, 0.67]");
+        let iv = Value::Interval(Interval::new(0.126, 0.134).unwrap());
+        assert_eq!(iv.fmt_with(&format(Option::Some(2), Option::None)), "[0.12, 0.14]");
+        let u = Value::Uncertain(Uncertain::exact(1.234).hypot(0.0123));
+        assert_eq!(u.fmt_with(&format(Option::Some(2), Option::None)), "1.23 ± 0.01");
+        assert_eq!(Value::Float(2.5).fmt_with(&format(Option::None, Option::Some(3))), "2.50");
+    }
+
+    fn ratio(n: i64, d: i64) -> Value {
+        Value::from_ratio(Rational::new(Integer::from(n), Integer::from(d)))
+    }
+
+    #[test]
+    fn rational_arithmetic() {
+        let ctx = Context::default();
+        let int = |i: i64| Value::Int(Integer::from(i));
+        assert_eq!(int(1).div(int(3), &ctx).unwrap().to_string(), "1/3");
+        assert_eq!(int(6).div(int(3), &ctx).unwrap().to_string(), "2");
+        assert_eq!(ratio(1, 3).add(ratio(1, 6), &ctx).unwrap().to_string(), "1/2");
+        assert_eq!(ratio(1, 2).sub(ratio(1, 2), &ctx).unwrap().to_string(), "0");
+        assert_eq!(ratio(2, 3).mul(int(3), &ctx).unwrap().to_string(), "2");
+        assert_eq!(ratio(2, 3).pow(int(-2), &ctx).unwrap().to_string(), "9/4");
+        assert_eq!(ratio(-7, 2).neg(&ctx).unwrap().to_string(), "7/2");
+        assert_eq!(ratio(1, 4).add(Value::Float(0.5), &ctx).unwrap().to_string(), "0.75");
+        assert_eq!(ratio(1, 3).div(int(0), &ctx).unwrap().to_string(), "inf");
+        let strict = Context { strict_float: true, ..Context::default() };
+        assert!(ratio(1, 3).div(int(0), &strict).is_err());
+        assert!(ratio(1, 3).not(&ctx).is_err());
+    }
+
+    #[test]
+    fn complex_arithmetic() {
+        let ctx = Context::default();
+        let i = || Value::Complex(Complex::new(0.0, 1.0));
+        let int = |n: i64| Value::Int(Integer::from(n));
+        assert_eq!(i().pow(int(2), &ctx).unwrap().to_string(), "-1");
+        assert_eq!(i().pow(int(3), &ctx).unwrap().to_string(), "-1i");
+        assert_eq!(i().pow(int(-1), &ctx).unwrap().to_string(), "-1i");
+        let one_plus_i = || int(1).add(i(), &ctx).unwrap();
+        assert_eq!(one_plus_i().to_string(), "1+1i");
+        assert_eq!(one_plus_i().pow(int(2), &ctx).unwrap().to_string(), "2i");
+        let one_minus_i = int(1).sub(i(), &ctx).unwrap();
+        assert_eq!(one_plus_i().mul(one_minus_i, &ctx).unwrap().to_string(), "2");
+        assert_eq!(int(1).div(one_plus_i(), &ctx).unwrap().to_string(), "0.5-0.5i");
+        assert_eq!(i().neg(&ctx).unwrap().to_string(), "-1i");
+        let root = i().pow(Value::Float(0.5), &ctx).unwrap().as_complex();
+        assert!((root - Complex::new(0.5f64.sqrt(), 0.5f64.sqrt())).norm() < 1e-15);
+    }
+
+    #[test]
+    fn floating_bitwise_not() {
+        let ctx = Context { allow_floating_bitwise_operations: true, ..Context::default() };
+        assert_eq!(Value::Float(2.5).not(&ctx).unwrap().to_string(), "-3");
+        assert_eq!(ratio(7, 2).not(&ctx).unwrap().to_string(), "-4");
+        let ctx = Context { int_width: Option::Some(8), unsigned: true, ..ctx };
+        assert_eq!(Value::Float(2.5).not(&ctx).unwrap().to_string(), "253");
+    }
+}
@@ -1,5 +1,6 @@
 use clap::{Parser, Subcommand, ArgAction};
 
+use crate::calc::format::Notation;
 use crate::calc::value::Value;
 
 
@@ -17,6 +18,128 @@ pub struct Args {
     )]
     pub allow_floating_bitwise_operation: bool,
 
+    #[arg(
+        short = 'D',
+        long = "decimal",
+        help = "show rational results as their decimal expansion",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+    )]
+    pub decimal: bool,
+
+    #[arg(
+        short = 'F',
+        long = "fraction",
+        value_name = "MAX_DENOMINATOR",
+        help = "show float results that round a simple fraction as that fraction",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1000",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "decimal",
+    )]
+    pub fraction: Option<u64>,
+
+    #[arg(
+        short = 'P',
+        long = "polar",
+        help = "show complex results in polar form",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+    )]
+    pub polar: bool,
+
+    #[arg(
+        long = "precision",
+        value_name = "N",
+        help = "evaluate floating point numbers with N significant digits",
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    pub precision: Option<u64>,
+
+    #[arg(
+        long = "promote-to-float",
+        help = "fall back to floating point on integer overflow or division by zero",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+    )]
+    pub promote_to_float: bool,
+
+    #[arg(
+        short = 'S',
+        long = "strict",
+        help = "report division by zero and inf/NaN results as errors instead of following IEEE",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+    )]
+    pub strict: bool,
+
+    #[arg(
+        long = "int-width",
+        value_name = "BITS",
+        help = "wrap integer arithmetic at a fixed width of BITS bits, two's complement unless --unsigned",
+        value_parser = clap::value_parser!(u32).range(1..=4096),
+    )]
+    pub int_width: Option<u32>,
+
+    #[arg(
+        long = "unsigned",
+        help = "treat fixed-width integers as unsigned",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        requires = "int_width",
+    )]
+    pub unsigned: bool,
+
+    #[arg(
+        long = "report-overflow",
+        help = "report fixed-width integer overflow as an error instead of wrapping",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        requires = "int_width",
+    )]
+    pub report_overflow: bool,
+
+    #[arg(
+        long = "output-base",
+        value_name = "BASE",
+        help = "show integer results in base 2, 8, 10 or 16",
+        value_parser = parse_output_base,
+    )]
+    pub output_base: Option<u32>,
+
+    #[arg(
+        long = "fixed",
+        value_name = "N",
+        help = "show results with N digits after the decimal point",
+        conflicts_with = "significant",
+    )]
+    pub fixed: Option<usize>,
+
+    #[arg(
+        long = "sig",
+        value_name = "N",
+        help = "round results to N significant figures",
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    pub significant: Option<u64>,
+
+    #[arg(
+        long = "notation",
+        value_name = "STYLE",
+        help = "write results in plain, sci (scientific) or eng (engineering) notation",
+        value_parser = parse_notation,
+    )]
+    pub notation: Option<Notation>,
+
+    #[arg(
+        long = "separators",
+        help = "group the integer digits of results in thousands",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+    )]
+    pub separators: bool,
+
     #[arg(
         long = "ignore-funcs",
         help = "disallowed builtin functions",
@@ -46,6 +169,13 @@ pub struct Args {
     )]
     pub extra_consts: Vec<(String, Value)>,
 
+    #[arg(
+        long = "units",
+        value_name = "FILE",
+        help = "load extra units from FILE, one `NAME = EXPR` definition per line",
+    )]
+    pub units: Option<String>,
+
     #[arg(help = "expression to evaluate (omit this to open REPL)")]
     pub expr: Option<String>,
 
@@ -99,6 +229,22 @@ pub enum FuncSub {
 }
 
 
+pub fn parse_output_base(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Result::Ok(base) if [2, 8, 10, 16].contains(&base) => Result::Ok(base),
+        _ => Result::Err(format!("expected 2, 8, 10 or 16, got {}", s)),
+    }
+}
+
+pub fn parse_notation(s: &str) -> Result<Notation, String> {
+    match s {
+        "plain" => Result::Ok(Notation::Plain),
+        "sci" => Result::Ok(Notation::Scientific),
+        "eng" => Result::Ok(Notation::Engineering),
+        _ => Result::Err(format!("expected plain, sci or eng, got {}", s)),
+    }
+}
+
 fn parse_extra_consts(s: &str) -> Result<(String, Value), String> {
     let (k, v) = s
         .split_once('=')
@@ -5,6 +5,10 @@ use calc::context::Context;
 use calc::eval::eval;
 use calc::functions::builtin_funcs;
 use calc::constants::builtin_consts;
+use calc::common::{Integer, Rational};
+use calc::format::Format;
+use calc::fraction::DEFAULT_MAX_DENOMINATOR;
+use calc::units::{builtin_units, find_unit, Unit};
 use calc::lexer::Lexer;
 use calc::parser::Parser;
 use calc::token::Token;
@@ -16,7 +20,13 @@ use rustyline::DefaultEditor;
 
 fn main() {
     let args = cli::Args::parse();
-    let ctx = create_context(&args);
+    let mut ctx = create_context(&args);
+    if let Some(path) = &args.units {
+        if let Result::Err(e) = load_units(&mut ctx, path) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
 
     match args.command {
         Some(cli::CalcCommand::Const(cmd)) => match cmd.sub {
@@ -55,13 +65,13 @@ fn main() {
 
     if let Some(expr) = args.expr {
         match evaluate(&ctx, expr) {
-            Result::Ok(v) => println!("{}", v),
+            Result::Ok(v) => println!("{}", display(&ctx, v)),
             Result::Err(e) => eprintln!("{:?}", e),
         };
         return;
     }
 
-    run_repl(&ctx);
+    run_repl(&mut ctx);
 }
 
 fn evaluate(ctx: &Context, expr: String) -> Result<Value, String> {
@@ -70,20 +80,45 @@ fn evaluate(ctx: &Context, expr: String) -> Result<Value, String> {
         Result::Ok(t) => t,
         Result::Err(e) => {
             let msg = format!("ParserError: {:?}", e);
-            return Result::Err(String::from(msg));
+            return Result::Err(msg);
         }
     };
-    match eval(&ctx, &expr) {
+    match eval(ctx, &expr) {
         Result::Ok(v) => Result::Ok(v),
         Result::Err(e) => {
             let msg = format!("EvalError: {:?}", e);
-            return Result::Err(String::from(msg));
+            Result::Err(msg)
         }
     }
 }
 
-fn run_repl(ctx: &Context) {
-    println!("Calc REPL. Use 'exit' to quit.");
+fn display(ctx: &Context, value: Value) -> String {
+    let measured = value.clone().into_si().is_quantity();
+    let value = if ctx.rational_as_decimal || measured { value.into_decimal(ctx.precision) } else { value };
+    let value = match ctx.float_as_fraction {
+        Option::Some(max_den) if !measured => value.into_fraction(&Integer::from(max_den)),
+        _ => value,
+    };
+    if let Value::Radix(v, base) = value {
+        return v.fmt_radix(base, ctx.int_width);
+    }
+    if let Option::Some(base) = ctx.output_base {
+        return value.fmt_radix(base, ctx.int_width);
+    }
+    if let Option::Some(width) = ctx.int_width {
+        return value.fmt_bits(width);
+    }
+    if ctx.complex_as_polar {
+        return value.fmt_polar();
+    }
+    if ctx.format != Format::default() {
+        return value.fmt_with(&ctx.format);
+    }
+    format!("{}", value)
+}
+
+fn run_repl(ctx: &mut Context) {
+    println!("Calc REPL. Use 'exit' to quit, ':set OPTION VALUE' to change settings.");
     let mut rl = DefaultEditor::new().unwrap();
 
     loop {
@@ -94,8 +129,15 @@ fn run_repl(ctx: &Context) {
                     continue;
                 }
                 rl.add_history_entry(input.as_str()).unwrap();
+                if let Option::Some(command) = input.trim().strip_prefix(':') {
+                    match run_command(ctx, command) {
+                        Result::Ok(msg) => println!("{}", msg),
+                        Result::Err(e) => println!("CommandError: {}", e),
+                    }
+                    continue;
+                }
                 match evaluate(ctx, input) {
-                    Result::Ok(v) => println!("{}", v),
+                    Result::Ok(v) => println!("{}", display(ctx, v)),
                     Result::Err(e) => println!("{}", e),
                 }
             }
@@ -104,11 +146,100 @@ fn run_repl(ctx: &Context) {
     }
 }
 
+// REPL counterparts of the command line flags, e.g. `:set int-width 32`
+fn run_command(ctx: &mut Context, command: &str) -> Result<String, String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let (option, value) = match words[..] {
+        ["set", option, value] => (option, value),
+        _ => return Result::Err(format!("expected ':set OPTION VALUE', got ':{}'", command)),
+    };
+
+    match option {
+        "int-width" if value == "off" => ctx.int_width = Option::None,
+        "int-width" => match value.parse::<u32>() {
+            Result::Ok(width) if (1..=4096).contains(&width) => ctx.int_width = Option::Some(width),
+            _ => return Result::Err(format!("expected a width from 1 to 4096 or 'off', got {}", value)),
+        },
+        "unsigned" => ctx.unsigned = parse_switch(value)?,
+        "report-overflow" => ctx.report_overflow = parse_switch(value)?,
+        "output-base" if value == "off" => ctx.output_base = Option::None,
+        "output-base" => ctx.output_base = Option::Some(cli::parse_output_base(value)?),
+        "fixed" | "sig" if value == "off" => {
+            ctx.format.decimals = Option::None;
+            ctx.format.significant = Option::None;
+        },
+        "fixed" => {
+            ctx.format.decimals = Option::Some(value.parse().map_err(|_| format!("expected a digit count, got {}", value))?);
+            ctx.format.significant = Option::None;
+        },
+        "sig" => match value.parse::<usize>() {
+            Result::Ok(n) if n > 0 => {
+                ctx.format.significant = Option::Some(n);
+                ctx.format.decimals = Option::None;
+            },
+            _ => return Result::Err(format!("expected a positive digit count, got {}", value)),
+        },
+        "fraction" if value == "off" => ctx.float_as_fraction = Option::None,
+        "fraction" if value == "on" => ctx.float_as_fraction = Option::Some(DEFAULT_MAX_DENOMINATOR),
+        "fraction" => match value.parse::<u64>() {
+            Result::Ok(max_den) if max_den > 0 => ctx.float_as_fraction = Option::Some(max_den),
+            _ => return Result::Err(format!("expected 'on', 'off' or a denominator limit, got {}", value)),
+        },
+        "notation" => ctx.format.notation = cli::parse_notation(value)?,
+        "separators" => ctx.format.separators = parse_switch(value)?,
+        _ => return Result::Err(format!("unknown option '{}'", option)),
+    }
+    Result::Ok(format!("{} = {}", option, value))
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" => Result::Ok(true),
+        "off" => Result::Ok(false),
+        _ => Result::Err(format!("expected 'on' or 'off', got {}", value)),
+    }
+}
+
+fn load_units(ctx: &mut Context, path: &str) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (name, expr) = line
+            .split_once('=')
+            .ok_or(format!("{}:{}: expected NAME = EXPR", path, n + 1))?;
+
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Result::Err(format!("{}:{}: invalid unit name '{}'", path, n + 1, name));
+        }
+        if find_unit(&ctx.units, name).is_some() {
+            return Result::Err(format!("{}:{}: unit '{}' is already defined", path, n + 1, name));
+        }
+
+        let value = evaluate(ctx, expr.trim().to_string())
+            .map_err(|e| format!("{}:{}: {}", path, n + 1, e))?
+            .into_si();
+        let dim = value.dim();
+        let magnitude = value.magnitude();
+        let factor = magnitude
+            .as_ratio()
+            .or_else(|| Rational::from_float(magnitude.as_float()))
+            .ok_or(format!("{}:{}: expected a real number, got {}", path, n + 1, magnitude))?;
+        ctx.units.insert(name.to_string(), Unit::plain(factor, dim));
+    }
+
+    Result::Ok(())
+}
+
 fn create_context(args: &cli::Args) -> Context {
-    let builtin_constants = builtin_consts();
+    let builtin_constants = builtin_consts(args.precision);
     let builtin_constants = builtin_constants.keys().cloned().collect::<Vec<String>>();
 
-    let mut consts = builtin_consts();
+    let mut consts = builtin_consts(args.precision);
     for c_name in &args.ignore_consts {
         consts.remove(c_name);
     }
@@ -127,6 +258,23 @@ fn create_context(args: &cli::Args) -> Context {
     Context {
         consts,
         funcs,
+        units: builtin_units(),
         allow_floating_bitwise_operations: args.allow_floating_bitwise_operation,
+        rational_as_decimal: args.decimal,
+        float_as_fraction: args.fraction,
+        complex_as_polar: args.polar,
+        precision: args.precision,
+        promote_to_float: args.promote_to_float,
+        strict_float: args.strict,
+        int_width: args.int_width,
+        unsigned: args.unsigned,
+        report_overflow: args.report_overflow,
+        output_base: args.output_base,
+        format: Format {
+            notation: args.notation.unwrap_or_default(),
+            decimals: args.fixed,
+            significant: args.significant.map(|n| n as usize),
+            separators: args.separators,
+        },
     }
 }